@@ -0,0 +1,98 @@
+use crate::observer::{EngineEvent, EngineObserver};
+use crate::output::{OutputFormat, OutputWriter};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+struct Feed<W: Write> {
+    // Taken by `finish`.
+    writer: Option<OutputWriter<W>>,
+    // A row can cause two events (a chargeback also locks the account) but is one change.
+    last_seq: Option<u64>,
+    unflushed: bool,
+    // Observers cannot return errors, so the first one is kept for `flush` and `finish`.
+    error: Option<io::Error>,
+}
+
+// Writes a record for every row that changes an account, with the balance before and after
+// it, as the engine applies rows. All clones write to the same stream: the one subscribed
+// to the engine adds records, and the caller's decides when they are flushed and finished.
+pub struct ChangeFeed<W: Write> {
+    feed: Arc<Mutex<Feed<W>>>,
+}
+
+impl<W: Write> Clone for ChangeFeed<W> {
+    fn clone(&self) -> Self {
+        Self {
+            feed: Arc::clone(&self.feed),
+        }
+    }
+}
+
+impl<W: Write> ChangeFeed<W> {
+    pub fn new(writer: W, format: OutputFormat) -> Self {
+        Self {
+            feed: Arc::new(Mutex::new(Feed {
+                writer: Some(OutputWriter::new(writer, format)),
+                last_seq: None,
+                unflushed: false,
+                error: None,
+            })),
+        }
+    }
+
+    fn feed(&self) -> MutexGuard<'_, Feed<W>> {
+        self.feed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Only flushes when something was written since the last time, so it is cheap to call
+    // after every row.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut feed = self.feed();
+        if let Some(e) = feed.error.take() {
+            return Err(e);
+        }
+        if !feed.unflushed {
+            return Ok(());
+        }
+        feed.unflushed = false;
+        match feed.writer.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    // Ends the stream, or returns the error that stopped it.
+    pub fn finish(&self) -> io::Result<W> {
+        let mut feed = self.feed();
+        if let Some(e) = feed.error.take() {
+            return Err(e);
+        }
+        match feed.writer.take() {
+            Some(writer) => writer.finish(),
+            None => Err(io::Error::other("the change feed is already finished")),
+        }
+    }
+}
+
+impl<W: Write + Send> EngineObserver for ChangeFeed<W> {
+    fn on_event(&mut self, event: &EngineEvent) {
+        let mut feed = self.feed();
+        if event.before == event.after || feed.last_seq == Some(event.seq) || feed.error.is_some() {
+            return;
+        }
+        feed.last_seq = Some(event.seq);
+
+        let Some(writer) = feed.writer.as_mut() else {
+            return;
+        };
+        match writer.write(&event.to_change_output()) {
+            Ok(()) => feed.unflushed = true,
+            Err(e) => feed.error = Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,60 @@
+use super::*;
+use crate::Engine;
+use crate::models::TransactionType;
+use crate::test_support::{new_tx, observed};
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_writes_one_record_per_changing_row() {
+    let (mut engine, feed) = observed(ChangeFeed::new(Vec::new(), OutputFormat::Csv));
+
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))));
+    engine.process(new_tx(1, 2, TransactionType::Withdrawal, Some(dec!(50.0))));
+    engine.process(new_tx(1, 1, TransactionType::Dispute, None));
+    engine.process(new_tx(1, 1, TransactionType::Chargeback, None));
+
+    assert_eq!(
+        String::from_utf8(feed.finish().unwrap()).unwrap(),
+        "seq,client,tx,old_available,old_held,old_locked,new_available,new_held,new_locked\n\
+         0,1,1,0.0,0.0,false,10.0,0.0,false\n\
+         2,1,1,10.0,0.0,false,0.0,10.0,false\n\
+         3,1,1,0.0,10.0,false,0.0,0.0,true\n"
+    );
+}
+
+#[test]
+fn test_json_lines_write_decimals_as_strings() {
+    let (mut engine, feed) = observed(ChangeFeed::new(Vec::new(), OutputFormat::Jsonl));
+
+    engine.process(new_tx(7, 1, TransactionType::Deposit, Some(dec!(2.5))));
+
+    assert_eq!(
+        String::from_utf8(feed.finish().unwrap()).unwrap(),
+        "{\"seq\":0,\"client\":7,\"tx\":1,\"old_available\":\"0.0\",\"old_held\":\"0.0\",\
+         \"old_locked\":false,\"new_available\":\"2.5\",\"new_held\":\"0.0\",\"new_locked\":false}\n"
+    );
+}
+
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_keeps_the_first_write_error() {
+    let feed = ChangeFeed::new(Broken, OutputFormat::Jsonl);
+    let mut engine = Engine::new();
+    engine.subscribe(feed.clone());
+
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(1.0))));
+    engine.process(new_tx(1, 2, TransactionType::Deposit, Some(dec!(1.0))));
+
+    assert_eq!(feed.flush().unwrap_err().to_string(), "disk full");
+}
@@ -0,0 +1,75 @@
+use crate::Engine;
+use crate::codec::{invalid_data, read_bool, read_u16, read_u64, write_bool};
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"TXCK";
+const VERSION: u16 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputPosition {
+    pub byte: u64,
+    pub line: u64,
+    pub record: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    // Which of the run's inputs, in the order they were given, the position refers to.
+    pub input: u64,
+    pub position: InputPosition,
+    pub event_log_len: Option<u64>,
+}
+
+// The input position and the engine state are written to the same file, so resuming
+// from it never applies a row twice or skips one.
+pub fn save<W: Write>(mut writer: W, engine: &Engine, checkpoint: &Checkpoint) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+
+    writer.write_all(&checkpoint.input.to_le_bytes())?;
+    writer.write_all(&checkpoint.position.byte.to_le_bytes())?;
+    writer.write_all(&checkpoint.position.line.to_le_bytes())?;
+    writer.write_all(&checkpoint.position.record.to_le_bytes())?;
+
+    write_bool(&mut writer, checkpoint.event_log_len.is_some())?;
+    writer.write_all(&checkpoint.event_log_len.unwrap_or(0).to_le_bytes())?;
+
+    engine.snapshot(writer)
+}
+
+pub fn load<R: Read>(mut reader: R) -> io::Result<(Engine, Checkpoint)> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a checkpoint file"));
+    }
+
+    let version = read_u16(&mut reader)?;
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "unsupported checkpoint version {version}"
+        )));
+    }
+
+    let input = read_u64(&mut reader)?;
+    let position = InputPosition {
+        byte: read_u64(&mut reader)?,
+        line: read_u64(&mut reader)?,
+        record: read_u64(&mut reader)?,
+    };
+
+    let has_event_log = read_bool(&mut reader)?;
+    let event_log_len = read_u64(&mut reader)?;
+
+    let engine = Engine::restore(reader)?;
+    let checkpoint = Checkpoint {
+        input,
+        position,
+        event_log_len: has_event_log.then_some(event_log_len),
+    };
+
+    Ok((engine, checkpoint))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,47 @@
+use super::*;
+use crate::models::TransactionType;
+use crate::test_support::new_tx;
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_load_returns_saved_position_and_engine() {
+    let mut engine = Engine::new();
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))));
+    let checkpoint = Checkpoint {
+        input: 1,
+        position: InputPosition {
+            byte: 42,
+            line: 3,
+            record: 2,
+        },
+        event_log_len: Some(128),
+    };
+
+    let mut buffer = Vec::new();
+    save(&mut buffer, &engine, &checkpoint).unwrap();
+    let (restored, loaded) = load(buffer.as_slice()).unwrap();
+
+    assert_eq!(loaded, checkpoint);
+    assert_eq!(
+        restored.get_accounts().get(&1).unwrap().available,
+        dec!(10.0)
+    );
+}
+
+#[test]
+fn test_load_without_event_log() {
+    let mut buffer = Vec::new();
+    save(&mut buffer, &Engine::new(), &Checkpoint::default()).unwrap();
+
+    let (_, loaded) = load(buffer.as_slice()).unwrap();
+
+    assert_eq!(loaded.event_log_len, None);
+}
+
+#[test]
+fn test_load_rejects_snapshot_file() {
+    let mut buffer = Vec::new();
+    Engine::new().snapshot(&mut buffer).unwrap();
+
+    assert!(load(buffer.as_slice()).is_err());
+}
@@ -0,0 +1,619 @@
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use transaction_engine::input;
+use transaction_engine::{
+    AccountOrder, Compression, CsvDialect, InputFormat, LogSync, OutputFormat, PointInTime,
+};
+
+// Exit statuses besides success; clap itself exits with 2 on an invalid command line.
+pub const EXIT_FAILURE: u8 = 1;
+pub const EXIT_INVALID_INPUT: u8 = 3;
+
+// Where `serve` listens when no source is given.
+const DEFAULT_LISTEN: &str = "127.0.0.1:7878";
+
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  the run failed (I/O error, corrupt state or log, ledger mismatch, ...)
+  2  invalid command line
+  3  `validate` found issues in the inputs";
+
+#[derive(Parser)]
+#[command(
+    name = "transaction-engine",
+    version,
+    about = "Applies deposits, withdrawals and disputes to client accounts and writes the balances",
+    after_help = EXIT_CODES
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    #[command(flatten)]
+    pub logging: LogArgs,
+}
+
+impl Cli {
+    // `cargo run -- transactions.csv` predates subcommands and still means `process`.
+    pub fn parse_args() -> Self {
+        let mut args: Vec<OsString> = env::args_os().collect();
+        if !names_subcommand(&args[1..]) {
+            args.insert(1, OsString::from("process"));
+        }
+
+        Self::parse_from(args)
+    }
+}
+
+// Whether the first argument that is not a global option is a subcommand (or asks for help),
+// rather than an input or an option of `process`.
+fn names_subcommand(args: &[OsString]) -> bool {
+    let mut args = args.iter().map(|arg| arg.to_str().unwrap_or_default());
+
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" | "-V" | "--version" | "help" => return true,
+            "--log-level" | "--log-format" => {
+                args.next();
+            }
+            _ if arg.starts_with("--log-level=") || arg.starts_with("--log-format=") => {}
+            _ => {
+                return <Cli as CommandFactory>::command()
+                    .get_subcommands()
+                    .any(|command| command.get_name() == arg);
+            }
+        }
+    }
+
+    // Without any argument clap prints the help.
+    true
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Apply transactions and write the resulting accounts (the default)
+    Process(ProcessArgs),
+    /// Check inputs for issues without applying them
+    Validate(ValidateArgs),
+    /// Apply transactions and write a summary of the run instead of the accounts
+    Stats(StatsArgs),
+    /// Rebuild the accounts from event logs
+    Replay(ReplayArgs),
+    /// Accept transaction rows over TCP, Unix sockets or named pipes until stopped
+    Serve(ServeArgs),
+    /// Answer HTTP requests for transactions, accounts, history and disputes until stopped
+    Api(ApiArgs),
+    /// Write every event of one client with its balance after it
+    History(HistoryArgs),
+    /// Write one client's balance at a point in time
+    BalanceAt(BalanceAtArgs),
+}
+
+#[derive(Args)]
+pub struct ProcessArgs {
+    /// Transaction files, processed in the order given; `-` reads stdin
+    #[arg(required = true, value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    /// Interleave the inputs by timestamp instead of reading them one after another
+    #[arg(long, conflicts_with = "checkpoint", help_heading = "Input")]
+    pub merge: bool,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Append every parsed row to this log before applying it
+    #[arg(long, value_name = "LOG_FILE", help_heading = "State")]
+    pub event_log: Option<PathBuf>,
+
+    /// Force each logged row to disk before applying it, or only hand it to the OS
+    #[arg(
+        long,
+        value_name = "record|os",
+        default_value = "record",
+        requires = "event_log",
+        help_heading = "State"
+    )]
+    pub event_log_sync: LogSync,
+
+    #[command(flatten)]
+    pub follow: FollowArgs,
+
+    #[command(flatten)]
+    pub checkpoint: CheckpointArgs,
+
+    #[command(flatten)]
+    pub state: StateArgs,
+
+    #[command(flatten)]
+    pub metrics: MetricsArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+
+    #[command(flatten)]
+    pub changes: ChangesArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    #[command(flatten)]
+    pub order: OrderArgs,
+}
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Transaction files, checked in the order given; `-` reads stdin
+    #[arg(required = true, value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Transaction files, processed in the order given; `-` reads stdin
+    #[arg(required = true, value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub state: StateArgs,
+
+    /// Format of the summary
+    #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+    pub stats_format: StatsFormat,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// Event logs written with `--event-log`, replayed in the order given; `-` reads stdin
+    #[arg(required = true, value_name = "LOG_FILE")]
+    pub logs: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub state: StateArgs,
+
+    #[command(flatten)]
+    pub metrics: MetricsArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+
+    #[command(flatten)]
+    pub changes: ChangesArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    #[command(flatten)]
+    pub order: OrderArgs,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to accept connections on [default: 127.0.0.1:7878 without --unix or --fifo]
+    #[arg(long, value_name = "HOST:PORT")]
+    pub listen: Option<String>,
+
+    /// Unix domain socket to accept connections on, created at startup
+    #[cfg(unix)]
+    #[arg(long, value_name = "SOCKET")]
+    pub unix: Vec<PathBuf>,
+
+    /// Existing named pipe to read rows from; its writers share one stream of rows
+    #[cfg(unix)]
+    #[arg(long, value_name = "FIFO")]
+    pub fifo: Vec<PathBuf>,
+
+    /// Send nothing back on connections instead of a reply per row
+    #[arg(long)]
+    pub no_acks: bool,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Append every parsed row to this log before applying it
+    #[arg(long, value_name = "LOG_FILE", help_heading = "State")]
+    pub event_log: Option<PathBuf>,
+
+    /// Force each logged row to disk before applying it, or only hand it to the OS
+    #[arg(
+        long,
+        value_name = "record|os",
+        default_value = "record",
+        requires = "event_log",
+        help_heading = "State"
+    )]
+    pub event_log_sync: LogSync,
+
+    #[command(flatten)]
+    pub state: StateArgs,
+
+    #[command(flatten)]
+    pub metrics: MetricsArgs,
+
+    /// Write the accounts to this file on SIGUSR1 instead of to stdout
+    #[arg(long, value_name = "FILE", help_heading = "Output")]
+    pub accounts_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub changes: ChangesArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    #[command(flatten)]
+    pub order: OrderArgs,
+}
+
+impl ServeArgs {
+    // Only listens on TCP by default when no other source is given.
+    pub fn tcp_addr(&self) -> Option<&str> {
+        #[cfg(unix)]
+        let other_sources = !self.unix.is_empty() || !self.fifo.is_empty();
+        #[cfg(not(unix))]
+        let other_sources = false;
+
+        match &self.listen {
+            Some(addr) => Some(addr),
+            None if other_sources => None,
+            None => Some(DEFAULT_LISTEN),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ApiArgs {
+    /// Address to answer HTTP requests on
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:8080")]
+    pub listen: String,
+
+    /// Open the admin endpoints to requests with `Authorization: Bearer <TOKEN>`
+    #[arg(long, value_name = "TOKEN", env = "TRANSACTION_ENGINE_ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// Keep every row in memory to answer `GET /accounts/{client}/history`
+    #[arg(long)]
+    pub keep_history: bool,
+
+    #[command(flatten)]
+    pub state: StateArgs,
+
+    #[command(flatten)]
+    pub metrics: MetricsArgs,
+}
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    pub client: u16,
+
+    /// Transaction files applied after the event log; `-` reads stdin
+    #[arg(required_unless_present = "event_log", value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    /// Replay this log, written with `--event-log`, on top of the loaded state first
+    #[arg(long, value_name = "LOG_FILE", help_heading = "State")]
+    pub event_log: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub state: StateArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct BalanceAtArgs {
+    pub client: u16,
+
+    /// Transaction files applied after the event log; `-` reads stdin
+    #[arg(required_unless_present = "event_log", value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    /// Replay this log, written with `--event-log`, on top of the loaded state first
+    #[arg(long, value_name = "LOG_FILE", help_heading = "State")]
+    pub event_log: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub at: PointInTimeArgs,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub state: StateArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+pub struct PointInTimeArgs {
+    /// Right before the transaction with this id
+    #[arg(long, value_name = "TX_ID")]
+    pub before_tx: Option<u32>,
+
+    /// Right after the event with this sequence number
+    #[arg(long, value_name = "SEQUENCE")]
+    pub seq: Option<u64>,
+
+    /// After the last event at or before this timestamp
+    #[arg(long)]
+    pub timestamp: Option<u64>,
+}
+
+impl PointInTimeArgs {
+    pub fn point_in_time(&self) -> PointInTime {
+        match (self.before_tx, self.seq, self.timestamp) {
+            (Some(tx), _, _) => PointInTime::BeforeTx(tx),
+            (_, Some(seq), _) => PointInTime::Seq(seq),
+            (_, _, Some(timestamp)) => PointInTime::Timestamp(timestamp),
+            (None, None, None) => unreachable!("clap requires one of the options"),
+        }
+    }
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Input")]
+pub struct InputArgs {
+    /// Format of the inputs [default: from the file extension, else csv]
+    #[arg(long, value_name = "csv|jsonl")]
+    pub input_format: Option<InputFormat>,
+
+    /// Column separator, a single character or `tab`
+    #[arg(long, value_name = "CHAR", value_parser = parse_csv_char)]
+    pub delimiter: Option<u8>,
+
+    /// Quote character
+    #[arg(long, value_name = "CHAR", value_parser = parse_csv_char, conflicts_with = "no_quoting")]
+    pub quote: Option<u8>,
+
+    /// Read quote characters as ordinary data
+    #[arg(long)]
+    pub no_quoting: bool,
+
+    /// The first row is data, not a header
+    #[arg(long)]
+    pub no_headers: bool,
+
+    /// Names of the columns in order, used instead of the header row
+    #[arg(long, value_name = "NAME,...", value_delimiter = ',')]
+    pub columns: Option<Vec<String>>,
+
+    /// Read a column under another name, e.g. `kind=type`
+    #[arg(long, value_name = "FROM=TO", value_parser = parse_rename)]
+    pub rename_column: Vec<(String, String)>,
+}
+
+impl InputArgs {
+    pub fn format(&self, path: &Path) -> InputFormat {
+        self.input_format
+            .unwrap_or_else(|| InputFormat::from_path(path))
+    }
+
+    pub fn dialect(&self) -> CsvDialect {
+        let default = CsvDialect::default();
+
+        CsvDialect {
+            delimiter: self.delimiter.unwrap_or(default.delimiter),
+            quote: if self.no_quoting {
+                None
+            } else {
+                self.quote.or(default.quote)
+            },
+            has_headers: !self.no_headers,
+            columns: self.columns.clone(),
+            renames: self.rename_column.iter().cloned().collect(),
+        }
+    }
+}
+
+// A single ASCII character, with `tab` (or `\t`) for tab-separated files.
+fn parse_csv_char(arg: &str) -> Result<u8, String> {
+    match arg {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if arg.len() == 1 && arg.is_ascii() => Ok(arg.as_bytes()[0]),
+        _ => Err(String::from("expected a single ASCII character or `tab`")),
+    }
+}
+
+fn parse_rename(arg: &str) -> Result<(String, String), String> {
+    let Some((from, to)) = arg.split_once('=') else {
+        return Err(String::from("expected <from>=<to>"));
+    };
+    if !input::COLUMNS.contains(&to) {
+        return Err(format!(
+            "`{to}` is not one of {}",
+            input::COLUMNS.join(", ")
+        ));
+    }
+
+    Ok((from.to_string(), to.to_string()))
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "State")]
+pub struct StateArgs {
+    /// Start from a state saved by an earlier run
+    #[arg(long, value_name = "STATE_FILE")]
+    pub load_state: Option<PathBuf>,
+
+    /// Save the state at the end of the run
+    #[arg(long, value_name = "STATE_FILE")]
+    pub save_state: Option<PathBuf>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "State")]
+pub struct CheckpointArgs {
+    /// Periodically save the state and input position to this file
+    #[arg(long, value_name = "CHECKPOINT_FILE")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Rows between two checkpoints
+    #[arg(
+        long,
+        value_name = "ROWS",
+        default_value_t = 10_000,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "checkpoint"
+    )]
+    pub checkpoint_every: u64,
+
+    /// Continue an interrupted run from its checkpoint, if there is one
+    #[arg(long, requires = "checkpoint")]
+    pub resume: bool,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Follow")]
+pub struct FollowArgs {
+    /// Keep reading the last input as it grows, until stopped with SIGTERM or Ctrl-C
+    #[arg(long, conflicts_with = "merge")]
+    pub follow: bool,
+
+    /// Seconds between two writes of the accounts while following
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 60,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "follow"
+    )]
+    pub emit_every: u64,
+
+    /// Write every account each time, or only those changed since the last write
+    #[arg(long, value_enum, default_value_t = EmitMode::Snapshot, requires = "follow")]
+    pub emit: EmitMode,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmitMode {
+    Snapshot,
+    Changes,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Metrics")]
+pub struct MetricsArgs {
+    /// Write Prometheus metrics to this file at the end of the run
+    #[arg(long, value_name = "PROM_FILE")]
+    pub metrics_file: Option<PathBuf>,
+
+    /// Serve Prometheus metrics on /metrics while the run lasts
+    #[arg(long, value_name = "HOST:PORT")]
+    pub metrics_addr: Option<String>,
+}
+
+impl MetricsArgs {
+    pub fn enabled(&self) -> bool {
+        self.metrics_file.is_some() || self.metrics_addr.is_some()
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Statistics")]
+pub struct ReportArgs {
+    /// Print a summary of the run to stderr once it is over
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Write the summary of the run to this file
+    #[arg(long, value_name = "FILE")]
+    pub stats_file: Option<PathBuf>,
+
+    /// Format of the summary
+    #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+    pub stats_format: StatsFormat,
+}
+
+impl ReportArgs {
+    pub fn enabled(&self) -> bool {
+        self.stats || self.stats_file.is_some()
+    }
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Output")]
+pub struct OutputArgs {
+    /// Format of the rows written to stdout
+    #[arg(long, value_name = "csv|json|jsonl", default_value = "csv")]
+    pub output_format: OutputFormat,
+
+    /// Compress what is written to stdout
+    #[arg(long, value_name = "none|gzip|zstd", default_value = "none")]
+    pub compress_output: Compression,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Output")]
+pub struct ChangesArgs {
+    /// Stream a record for every row that changes an account to this file; `-` writes it to
+    /// stdout instead of the accounts
+    #[arg(long, value_name = "FILE")]
+    pub changes: Option<PathBuf>,
+
+    /// Format of the change records
+    #[arg(
+        long,
+        value_name = "csv|json|jsonl",
+        default_value = "jsonl",
+        requires = "changes"
+    )]
+    pub changes_format: OutputFormat,
+}
+
+impl ChangesArgs {
+    pub fn on_stdout(&self) -> bool {
+        self.changes.as_deref() == Some(Path::new("-"))
+    }
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Output")]
+pub struct OrderArgs {
+    /// Column the accounts are sorted by, ascending with ties in client order
+    #[arg(
+        long,
+        value_name = "client|available|held|total|locked",
+        default_value = "client"
+    )]
+    pub sort_by: AccountOrder,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Logging")]
+pub struct LogArgs {
+    /// Log filter, e.g. `debug` [default: RUST_LOG, else warn]
+    #[arg(long, global = true, value_name = "FILTER")]
+    pub log_level: Option<String>,
+
+    /// Write logs to stderr as plain text or one JSON object per line
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+}
+
+#[cfg(test)]
+mod tests;
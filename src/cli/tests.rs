@@ -0,0 +1,196 @@
+use super::*;
+
+fn args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+}
+
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from(std::iter::once("transaction-engine").chain(args.iter().copied())).unwrap()
+}
+
+#[test]
+fn test_command_definition_is_consistent() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn test_inputs_without_subcommand_mean_process() {
+    assert!(!names_subcommand(&args(&["transactions.csv"])));
+    assert!(!names_subcommand(&args(&["-"])));
+    assert!(!names_subcommand(&args(&[
+        "--load-state",
+        "engine.state",
+        "day2.csv"
+    ])));
+    assert!(!names_subcommand(&args(&[
+        "--log-level",
+        "debug",
+        "transactions.csv"
+    ])));
+}
+
+#[test]
+fn test_subcommands_are_found_after_global_options() {
+    assert!(names_subcommand(&args(&["validate", "transactions.csv"])));
+    assert!(names_subcommand(&args(&[
+        "--log-level",
+        "debug",
+        "replay",
+        "events.log"
+    ])));
+    assert!(names_subcommand(&args(&[
+        "--log-format=json",
+        "history",
+        "1"
+    ])));
+    assert!(names_subcommand(&args(&["--help"])));
+    assert!(names_subcommand(&args(&[])));
+}
+
+#[test]
+fn test_csv_options_build_dialect() {
+    let Command::Validate(validate) = parse(&[
+        "validate",
+        "transactions.csv",
+        "--delimiter",
+        "tab",
+        "--no-quoting",
+        "--no-headers",
+        "--columns",
+        "tx,type,client,amount",
+        "--rename-column",
+        "kind=type",
+    ])
+    .command
+    else {
+        panic!("expected validate");
+    };
+
+    let dialect = validate.input.dialect();
+    assert_eq!(dialect.delimiter, b'\t');
+    assert_eq!(dialect.quote, None);
+    assert!(!dialect.has_headers);
+    assert_eq!(dialect.columns.unwrap().len(), 4);
+    assert_eq!(dialect.renames["kind"], "type");
+}
+
+#[test]
+fn test_rejects_rename_to_unknown_column() {
+    assert!(
+        Cli::try_parse_from([
+            "transaction-engine",
+            "process",
+            "a.csv",
+            "--rename-column",
+            "a=b"
+        ])
+        .is_err()
+    );
+}
+
+#[test]
+fn test_resume_requires_checkpoint() {
+    assert!(Cli::try_parse_from(["transaction-engine", "process", "a.csv", "--resume"]).is_err());
+}
+
+#[test]
+fn test_balance_at_takes_one_point_in_time() {
+    let Command::BalanceAt(balance_at) =
+        parse(&["balance-at", "7", "--event-log", "events.log", "--seq", "3"]).command
+    else {
+        panic!("expected balance-at");
+    };
+    assert_eq!(balance_at.at.point_in_time(), PointInTime::Seq(3));
+
+    assert!(
+        Cli::try_parse_from([
+            "transaction-engine",
+            "balance-at",
+            "7",
+            "--event-log",
+            "events.log",
+            "--seq",
+            "3",
+            "--before-tx",
+            "9",
+        ])
+        .is_err()
+    );
+
+    // A saved state holds no history to query.
+    assert!(
+        Cli::try_parse_from([
+            "transaction-engine",
+            "balance-at",
+            "7",
+            "--load-state",
+            "engine.state",
+            "--seq",
+            "3",
+        ])
+        .is_err()
+    );
+}
+
+#[test]
+fn test_serve_listens_on_tcp_unless_other_sources_are_given() {
+    let Command::Serve(serve) = parse(&["serve"]).command else {
+        panic!("expected serve");
+    };
+    assert_eq!(serve.tcp_addr(), Some(DEFAULT_LISTEN));
+
+    let Command::Serve(serve) = parse(&["serve", "--fifo", "rows.fifo"]).command else {
+        panic!("expected serve");
+    };
+    assert_eq!(serve.tcp_addr(), None);
+
+    let Command::Serve(serve) =
+        parse(&["serve", "--unix", "rows.sock", "--listen", "0.0.0.0:7000"]).command
+    else {
+        panic!("expected serve");
+    };
+    assert_eq!(serve.tcp_addr(), Some("0.0.0.0:7000"));
+}
+
+#[test]
+fn test_follow_options_need_follow() {
+    let Command::Process(process) =
+        parse(&["process", "daily.csv", "--follow", "--emit", "changes"]).command
+    else {
+        panic!("expected process");
+    };
+    assert!(process.follow.follow);
+    assert!(process.follow.emit == EmitMode::Changes);
+    assert_eq!(process.follow.emit_every, 60);
+
+    for invalid in [
+        &["daily.csv", "--emit-every", "10"][..],
+        &["a.csv", "b.csv", "--follow", "--merge"],
+        &["daily.csv", "--follow", "--emit-every", "0"],
+    ] {
+        assert!(
+            Cli::try_parse_from(
+                ["transaction-engine", "process"]
+                    .into_iter()
+                    .chain(invalid.iter().copied())
+            )
+            .is_err()
+        );
+    }
+}
+
+#[test]
+fn test_changes_on_stdout_replace_the_accounts() {
+    let Command::Replay(replay) = parse(&["replay", "events.log", "--changes", "-"]).command else {
+        panic!("expected replay");
+    };
+    assert!(replay.changes.on_stdout());
+    assert!(replay.changes.changes_format == OutputFormat::Jsonl);
+
+    let Command::Process(process) =
+        parse(&["process", "a.csv", "--changes", "changes.jsonl"]).command
+    else {
+        panic!("expected process");
+    };
+    assert!(!process.changes.on_stdout());
+}
@@ -0,0 +1,78 @@
+use crate::models::TransactionType;
+use rust_decimal::Decimal;
+use std::io::{self, Read, Write};
+
+pub(crate) fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+pub(crate) fn write_len<W: Write>(writer: &mut W, len: usize) -> io::Result<()> {
+    writer.write_all(&(len as u64).to_le_bytes())
+}
+
+pub(crate) fn write_decimal<W: Write>(writer: &mut W, value: Decimal) -> io::Result<()> {
+    writer.write_all(&value.serialize())
+}
+
+pub(crate) fn write_bool<W: Write>(writer: &mut W, value: bool) -> io::Result<()> {
+    writer.write_all(&[value as u8])
+}
+
+pub(crate) fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub(crate) fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+pub(crate) fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub(crate) fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub(crate) fn read_decimal<R: Read>(reader: &mut R) -> io::Result<Decimal> {
+    let mut buf = [0u8; 16];
+    reader.read_exact(&mut buf)?;
+    Ok(Decimal::deserialize(buf))
+}
+
+pub(crate) fn read_bool<R: Read>(reader: &mut R) -> io::Result<bool> {
+    match read_u8(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        other => Err(invalid_data(format!("invalid boolean byte {other}"))),
+    }
+}
+
+pub(crate) fn type_code(r#type: &TransactionType) -> u8 {
+    match r#type {
+        TransactionType::Deposit => 0,
+        TransactionType::Withdrawal => 1,
+        TransactionType::Dispute => 2,
+        TransactionType::Resolve => 3,
+        TransactionType::Chargeback => 4,
+    }
+}
+
+pub(crate) fn type_from_code(code: u8) -> io::Result<TransactionType> {
+    match code {
+        0 => Ok(TransactionType::Deposit),
+        1 => Ok(TransactionType::Withdrawal),
+        2 => Ok(TransactionType::Dispute),
+        3 => Ok(TransactionType::Resolve),
+        4 => Ok(TransactionType::Chargeback),
+        other => Err(invalid_data(format!("unknown transaction type {other}"))),
+    }
+}
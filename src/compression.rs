@@ -0,0 +1,102 @@
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    // Decompresses while reading, so only the decoder's buffers are held in memory.
+    pub fn reader<'a, R: Read + 'a>(self, reader: R) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::None => Box::new(reader),
+            Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+        })
+    }
+
+    pub fn writer<W: Write>(self, writer: W) -> io::Result<CompressedWriter<W>> {
+        let encoder = match self {
+            Compression::None => Encoder::None(writer),
+            Compression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(writer, 0)?),
+        };
+
+        Ok(CompressedWriter { encoder })
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(compression: &str) -> Result<Self, Self::Err> {
+        match compression {
+            "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!("unknown compression `{compression}`")),
+        }
+    }
+}
+
+enum Encoder<W: Write> {
+    None(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+pub struct CompressedWriter<W: Write> {
+    encoder: Encoder<W>,
+}
+
+impl<W: Write> CompressedWriter<W> {
+    // Writes the end of the compressed stream; without it the output cannot be decompressed.
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = match self.encoder {
+            Encoder::None(writer) => writer,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            Encoder::None(writer) => writer.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            Encoder::None(writer) => writer.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,73 @@
+use super::*;
+
+const DATA: &[u8] = b"type,client,tx,amount\ndeposit,1,1,1.0\n";
+
+fn round_trip(compression: Compression) -> (Vec<u8>, Vec<u8>) {
+    let mut writer = compression.writer(Vec::new()).unwrap();
+    writer.write_all(DATA).unwrap();
+    let compressed = writer.finish().unwrap();
+
+    let mut decompressed = Vec::new();
+    compression
+        .reader(compressed.as_slice())
+        .unwrap()
+        .read_to_end(&mut decompressed)
+        .unwrap();
+
+    (compressed, decompressed)
+}
+
+#[test]
+fn test_from_path() {
+    assert_eq!(
+        Compression::from_path(Path::new("day.csv.gz")),
+        Compression::Gzip
+    );
+    assert_eq!(
+        Compression::from_path(Path::new("day.jsonl.zst")),
+        Compression::Zstd
+    );
+    assert_eq!(
+        Compression::from_path(Path::new("day.csv")),
+        Compression::None
+    );
+}
+
+#[test]
+fn test_gzip_round_trip() {
+    let (compressed, decompressed) = round_trip(Compression::Gzip);
+
+    assert_eq!(&compressed[..2], &[0x1f, 0x8b]);
+    assert_eq!(decompressed, DATA);
+}
+
+#[test]
+fn test_zstd_round_trip() {
+    let (compressed, decompressed) = round_trip(Compression::Zstd);
+
+    assert_eq!(&compressed[..4], &[0x28, 0xb5, 0x2f, 0xfd]);
+    assert_eq!(decompressed, DATA);
+}
+
+#[test]
+fn test_none_passes_data_through() {
+    let (compressed, decompressed) = round_trip(Compression::None);
+
+    assert_eq!(compressed, DATA);
+    assert_eq!(decompressed, DATA);
+}
+
+#[test]
+fn test_gzip_reads_concatenated_members() {
+    let mut compressed = round_trip(Compression::Gzip).0;
+    compressed.extend(round_trip(Compression::Gzip).0);
+
+    let mut decompressed = Vec::new();
+    Compression::Gzip
+        .reader(compressed.as_slice())
+        .unwrap()
+        .read_to_end(&mut decompressed)
+        .unwrap();
+
+    assert_eq!(decompressed, [DATA, DATA].concat());
+}
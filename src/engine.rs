@@ -1,9 +1,29 @@
-use crate::models::{Account, Deposit, Transaction, TransactionInput, TransactionType};
+use crate::history::{History, HistoryEntry, PointInTime};
+use crate::ledger::{self, Ledger, LedgerAccount, LedgerError};
+use crate::models::{
+    Account, AccountOrder, Balance, BatchRejected, Deposit, OpenDispute, Outcome, Rejection,
+    Transaction, TransactionInput, TransactionType,
+};
+use crate::observer::{EngineEvent, EngineObserver, EventKind};
+use crate::snapshot;
+use rust_decimal::Decimal;
 use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use tracing::{debug, debug_span, instrument, trace};
 
 pub struct Engine {
     accounts: HashMap<u16, Account>,
     transactions: HashMap<u32, Transaction>,
+    ledger: Ledger,
+    history: Option<History>,
+    next_seq: u64,
+    observers: Vec<Box<dyn EngineObserver>>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Engine {
@@ -11,6 +31,10 @@ impl Engine {
         Self {
             accounts: HashMap::new(),
             transactions: HashMap::new(),
+            ledger: Ledger::new(),
+            history: None,
+            next_seq: 0,
+            observers: Vec::new(),
         }
     }
 
@@ -18,6 +42,118 @@ impl Engine {
         &self.accounts
     }
 
+    // Unlike `get_accounts`, the order is the same on every run.
+    pub fn accounts(&self) -> impl Iterator<Item = (u16, &Account)> {
+        let mut client_ids: Vec<u16> = self.accounts.keys().copied().collect();
+        client_ids.sort_unstable();
+        client_ids
+            .into_iter()
+            .map(|client_id| (client_id, &self.accounts[&client_id]))
+    }
+
+    pub fn accounts_by(&self, order: AccountOrder) -> Vec<(u16, &Account)> {
+        let mut accounts: Vec<_> = self.accounts().collect();
+        accounts.sort_by(|(_, a), (_, b)| order.compare(a, b));
+        accounts
+    }
+
+    // Keeps an entry for every row processed from now on, for `history` and `balance_at`.
+    // The history grows with every row and is never saved in a snapshot, so it is off
+    // unless asked for. Points before any entry have the balances accounts have now.
+    pub fn keep_history(&mut self) {
+        if self.history.is_none() {
+            let starting = self
+                .accounts
+                .iter()
+                .map(|(&client_id, account)| (client_id, account.balance()))
+                .collect();
+            self.history = Some(History::starting_from(starting));
+        }
+    }
+
+    pub fn keeps_history(&self) -> bool {
+        self.history.is_some()
+    }
+
+    // Empty unless the history is kept.
+    pub fn history(&self, client_id: u16) -> &[HistoryEntry] {
+        match &self.history {
+            Some(history) => history.for_client(client_id),
+            None => &[],
+        }
+    }
+
+    pub fn balance_at(&self, client_id: u16, at: PointInTime) -> Option<Balance> {
+        self.history.as_ref()?.balance_at(client_id, at)
+    }
+
+    pub fn open_disputes(&self) -> Vec<OpenDispute> {
+        let mut disputes: Vec<OpenDispute> = self
+            .transactions
+            .values()
+            .filter_map(|transaction| match transaction {
+                Transaction::Deposit(deposit) if deposit.under_dispute => Some(OpenDispute {
+                    tx: deposit.tx,
+                    client: deposit.client_id,
+                    amount: deposit.amount,
+                }),
+                _ => None,
+            })
+            .collect();
+        disputes.sort_by_key(|dispute| dispute.tx);
+        disputes
+    }
+
+    // Keeps the journal entry of every transaction applied from now on, for
+    // `ledger().entries()`. Without it the ledger only holds balances.
+    pub fn keep_journal(&mut self) {
+        self.ledger.keep_entries();
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    pub fn check_ledger(&self) -> Result<(), LedgerError> {
+        self.ledger.check_balanced()?;
+
+        for (&client_id, account) in &self.accounts {
+            let available = self
+                .ledger
+                .balance(LedgerAccount::ClientAvailable(client_id));
+            let held = self.ledger.balance(LedgerAccount::ClientHeld(client_id));
+
+            if available != account.available || held != account.held {
+                return Err(LedgerError::AccountMismatch { client: client_id });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn snapshot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        snapshot::write(
+            &mut writer,
+            &self.accounts,
+            &self.transactions,
+            &self.ledger,
+            self.next_seq,
+        )
+    }
+
+    pub fn restore<R: Read>(mut reader: R) -> io::Result<Self> {
+        let state = snapshot::read(&mut reader)?;
+
+        Ok(Self {
+            accounts: state.accounts,
+            transactions: state.transactions,
+            ledger: state.ledger,
+            history: None,
+            next_seq: state.next_seq,
+            observers: Vec::new(),
+        })
+    }
+
     fn is_duplicated(&self, transaction: &TransactionInput) -> bool {
         let is_credit_or_debit = matches!(
             transaction.r#type,
@@ -26,119 +162,286 @@ impl Engine {
         is_credit_or_debit && self.transactions.contains_key(&transaction.tx)
     }
 
-    pub fn process(&mut self, transaction: TransactionInput) {
-        if self.is_duplicated(&transaction) {
-            return;
+    pub fn subscribe<O: EngineObserver + 'static>(&mut self, observer: O) {
+        self.observers.push(Box::new(observer));
+    }
+
+    fn balance_of(&self, client_id: u16) -> Balance {
+        self.accounts
+            .get(&client_id)
+            .map(Account::balance)
+            .unwrap_or_default()
+    }
+
+    pub fn process(&mut self, transaction: TransactionInput) -> Outcome {
+        let _span = debug_span!(
+            "process",
+            tx = transaction.tx,
+            client = transaction.client,
+            r#type = transaction.r#type.as_str()
+        )
+        .entered();
+
+        let before = self.balance_of(transaction.client);
+
+        let result = if self.is_duplicated(&transaction) {
+            Err(Rejection::Duplicate)
+        } else {
+            match transaction.r#type {
+                TransactionType::Deposit => self.handle_deposit(&transaction),
+                TransactionType::Withdrawal => self.handle_withdrawal(&transaction),
+                TransactionType::Dispute => self.handle_dispute(&transaction),
+                TransactionType::Resolve => self.handle_resolve(&transaction),
+                TransactionType::Chargeback => self.handle_chargeback(&transaction),
+            }
+        };
+
+        match result {
+            Ok(amount) => trace!(%amount, "transaction applied"),
+            Err(rejection) => debug!(reason = rejection.as_str(), "transaction rejected"),
+        }
+
+        let outcome = Outcome::from(result.map(|_| ()));
+        let after = self.balance_of(transaction.client);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if let Some(history) = &mut self.history {
+            history.record(seq, &transaction, outcome, after);
         }
 
-        match transaction.r#type {
-            TransactionType::Deposit => self.handle_deposit(transaction),
-            TransactionType::Withdrawal => self.handle_withdrawal(transaction),
-            TransactionType::Dispute => self.handle_dispute(transaction),
-            TransactionType::Resolve => self.handle_resolve(transaction),
-            TransactionType::Chargeback => self.handle_chargeback(transaction),
+        if !self.observers.is_empty() {
+            self.notify(&transaction, seq, result, before, after);
         }
+
+        outcome
+    }
+
+    fn notify(
+        &mut self,
+        transaction: &TransactionInput,
+        seq: u64,
+        result: Result<Decimal, Rejection>,
+        before: Balance,
+        after: Balance,
+    ) {
+        let mut kinds = Vec::with_capacity(2);
+        match result {
+            Ok(amount) => kinds.push(match transaction.r#type {
+                TransactionType::Deposit => EventKind::Deposited { amount },
+                TransactionType::Withdrawal => EventKind::Withdrawn { amount },
+                TransactionType::Dispute => EventKind::DisputeOpened { amount },
+                TransactionType::Resolve => EventKind::DisputeResolved { amount },
+                TransactionType::Chargeback => EventKind::ChargedBack { amount },
+            }),
+            Err(rejection) => kinds.push(EventKind::Rejected {
+                r#type: transaction.r#type,
+                rejection,
+            }),
+        }
+        if !before.locked && after.locked {
+            kinds.push(EventKind::AccountLocked);
+        }
+
+        for kind in kinds {
+            let event = EngineEvent {
+                seq,
+                client: transaction.client,
+                tx: transaction.tx,
+                timestamp: transaction.timestamp,
+                kind,
+                before,
+                after,
+            };
+            for observer in &mut self.observers {
+                observer.on_event(&event);
+            }
+        }
+    }
+
+    // All or nothing: the batch is first run against a scratch engine, and only applied
+    // here when every row of it would be accepted.
+    #[instrument(level = "debug", skip_all, fields(rows = batch.len()))]
+    pub fn process_batch(&mut self, batch: Vec<TransactionInput>) -> Result<(), BatchRejected> {
+        let mut scratch = self.scratch_for(&batch);
+        for (index, transaction) in batch.iter().enumerate() {
+            if let Outcome::Rejected(rejection) = scratch.process(transaction.clone()) {
+                debug!(index, reason = rejection.as_str(), "batch rejected");
+                return Err(BatchRejected { index, rejection });
+            }
+        }
+
+        for transaction in batch {
+            let outcome = self.process(transaction);
+            debug_assert!(outcome.is_applied());
+        }
+
+        Ok(())
     }
 
-    fn find_deposit_mut(
-        transactions: &mut HashMap<u32, Transaction>,
-        tx_id: u32,
-    ) -> Option<&mut Deposit> {
-        transactions.get_mut(&tx_id)?.as_deposit_mut()
+    // A row only reads the account of its client and the transaction it refers to, so
+    // copying those is enough to predict the outcome of every row of the batch.
+    fn scratch_for(&self, batch: &[TransactionInput]) -> Engine {
+        let mut scratch = Engine::new();
+
+        for transaction in batch {
+            if let Some(account) = self.accounts.get(&transaction.client) {
+                scratch.accounts.insert(transaction.client, account.clone());
+            }
+            if let Some(stored) = self.transactions.get(&transaction.tx) {
+                scratch.transactions.insert(transaction.tx, stored.clone());
+            }
+        }
+
+        scratch
     }
 
-    fn handle_deposit(&mut self, transaction: TransactionInput) {
-        let account = self
-            .accounts
-            .entry(transaction.client)
-            .or_insert_with(Account::new);
+    fn find_disputed_deposit_mut<'a>(
+        transactions: &'a mut HashMap<u32, Transaction>,
+        transaction: &TransactionInput,
+    ) -> Result<&'a mut Deposit, Rejection> {
+        let deposit = transactions
+            .get_mut(&transaction.tx)
+            .ok_or(Rejection::UnknownTransaction)?
+            .as_deposit_mut()
+            .ok_or(Rejection::NotADeposit)?;
+
+        if deposit.client_id != transaction.client {
+            return Err(Rejection::ClientMismatch);
+        }
+
+        Ok(deposit)
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    fn handle_deposit(&mut self, transaction: &TransactionInput) -> Result<Decimal, Rejection> {
+        let account = self.accounts.entry(transaction.client).or_default();
 
         if account.locked {
-            return;
+            return Err(Rejection::AccountLocked);
         }
 
-        let Some(deposit) = Deposit::try_new(&transaction) else {
-            return;
-        };
+        let deposit = Deposit::try_new(transaction).ok_or(Rejection::MissingAmount)?;
 
         account.available += deposit.amount;
+        self.ledger.record(
+            deposit.tx,
+            transaction.r#type,
+            ledger::transfer(
+                LedgerAccount::Settlement,
+                LedgerAccount::ClientAvailable(transaction.client),
+                deposit.amount,
+            ),
+        );
+        let amount = deposit.amount;
         self.transactions
             .insert(deposit.tx, Transaction::Deposit(deposit));
+
+        Ok(amount)
     }
 
-    fn handle_withdrawal(&mut self, transaction: TransactionInput) {
-        let account = self
-            .accounts
-            .entry(transaction.client)
-            .or_insert_with(Account::new);
+    #[instrument(level = "trace", skip_all)]
+    fn handle_withdrawal(&mut self, transaction: &TransactionInput) -> Result<Decimal, Rejection> {
+        let account = self.accounts.entry(transaction.client).or_default();
 
         if account.locked {
-            return;
+            return Err(Rejection::AccountLocked);
         }
 
-        let Some(amount) = transaction.amount else {
-            return;
-        };
+        let amount = transaction.amount.ok_or(Rejection::MissingAmount)?;
 
-        if account.available >= amount {
-            account.available -= amount;
-            self.transactions
-                .insert(transaction.tx, Transaction::Withdrawal);
+        if account.available < amount {
+            return Err(Rejection::InsufficientFunds);
         }
+
+        account.available -= amount;
+        self.ledger.record(
+            transaction.tx,
+            transaction.r#type,
+            ledger::transfer(
+                LedgerAccount::ClientAvailable(transaction.client),
+                LedgerAccount::Settlement,
+                amount,
+            ),
+        );
+        self.transactions
+            .insert(transaction.tx, Transaction::Withdrawal);
+
+        Ok(amount)
     }
 
-    fn handle_dispute(&mut self, transaction: TransactionInput) {
-        let Some(deposit) = Self::find_deposit_mut(&mut self.transactions, transaction.tx) else {
-            return;
-        };
+    #[instrument(level = "trace", skip_all)]
+    fn handle_dispute(&mut self, transaction: &TransactionInput) -> Result<Decimal, Rejection> {
+        let deposit = Self::find_disputed_deposit_mut(&mut self.transactions, transaction)?;
 
-        if deposit.under_dispute || deposit.client_id != transaction.client {
-            return;
+        if deposit.under_dispute {
+            return Err(Rejection::AlreadyDisputed);
         }
 
-        let account = self
-            .accounts
-            .entry(transaction.client)
-            .or_insert_with(Account::new);
+        let account = self.accounts.entry(transaction.client).or_default();
         account.available -= deposit.amount;
         account.held += deposit.amount;
         deposit.under_dispute = true;
+        self.ledger.record(
+            transaction.tx,
+            transaction.r#type,
+            ledger::transfer(
+                LedgerAccount::ClientAvailable(transaction.client),
+                LedgerAccount::ClientHeld(transaction.client),
+                deposit.amount,
+            ),
+        );
+
+        Ok(deposit.amount)
     }
 
-    fn handle_resolve(&mut self, transaction: TransactionInput) {
-        let Some(deposit) = Self::find_deposit_mut(&mut self.transactions, transaction.tx) else {
-            return;
-        };
+    #[instrument(level = "trace", skip_all)]
+    fn handle_resolve(&mut self, transaction: &TransactionInput) -> Result<Decimal, Rejection> {
+        let deposit = Self::find_disputed_deposit_mut(&mut self.transactions, transaction)?;
 
-        if !deposit.under_dispute || deposit.client_id != transaction.client {
-            return;
+        if !deposit.under_dispute {
+            return Err(Rejection::NotDisputed);
         }
 
-        let account = self
-            .accounts
-            .entry(transaction.client)
-            .or_insert_with(Account::new);
+        let account = self.accounts.entry(transaction.client).or_default();
         account.held -= deposit.amount;
         account.available += deposit.amount;
         deposit.under_dispute = false;
+        self.ledger.record(
+            transaction.tx,
+            transaction.r#type,
+            ledger::transfer(
+                LedgerAccount::ClientHeld(transaction.client),
+                LedgerAccount::ClientAvailable(transaction.client),
+                deposit.amount,
+            ),
+        );
+
+        Ok(deposit.amount)
     }
 
-    fn handle_chargeback(&mut self, transaction: TransactionInput) {
-        let Some(deposit) = Self::find_deposit_mut(&mut self.transactions, transaction.tx) else {
-            return;
-        };
+    #[instrument(level = "trace", skip_all)]
+    fn handle_chargeback(&mut self, transaction: &TransactionInput) -> Result<Decimal, Rejection> {
+        let deposit = Self::find_disputed_deposit_mut(&mut self.transactions, transaction)?;
 
-        if !deposit.under_dispute || deposit.client_id != transaction.client {
-            return;
+        if !deposit.under_dispute {
+            return Err(Rejection::NotDisputed);
         }
 
-        let account = self
-            .accounts
-            .entry(transaction.client)
-            .or_insert_with(Account::new);
+        let account = self.accounts.entry(transaction.client).or_default();
         account.held -= deposit.amount;
         account.locked = true;
         deposit.under_dispute = false;
+        self.ledger.record(
+            transaction.tx,
+            transaction.r#type,
+            ledger::transfer(
+                LedgerAccount::ClientHeld(transaction.client),
+                LedgerAccount::ChargebackLosses,
+                deposit.amount,
+            ),
+        );
+
+        Ok(deposit.amount)
     }
 }
 
@@ -1,22 +1,9 @@
 use super::*;
-use crate::models::TransactionType;
-use rust_decimal::Decimal;
+use crate::ledger::LedgerAccount;
+use crate::models::{BatchRejected, Outcome, Rejection, TransactionType};
+use crate::test_support::new_tx;
 use rust_decimal_macros::dec;
 
-fn new_tx(
-    client: u16,
-    tx: u32,
-    transaction_type: TransactionType,
-    amount: Option<Decimal>,
-) -> TransactionInput {
-    TransactionInput {
-        r#type: transaction_type,
-        client,
-        tx,
-        amount,
-    }
-}
-
 #[test]
 fn test_deposit_increases_balance_for_new_account() {
     let mut engine = Engine::new();
@@ -427,3 +414,296 @@ fn test_chargeback_ignored_when_tx_is_not_under_dispute() {
     assert_eq!(account.total(), dec!(100.0));
     assert!(!account.locked);
 }
+
+#[test]
+fn test_ledger_balanced_after_every_transaction_type() {
+    let mut engine = Engine::new();
+    engine.keep_journal();
+    let client_id = 1;
+
+    engine.process(new_tx(
+        client_id,
+        1,
+        TransactionType::Deposit,
+        Some(dec!(100.0)),
+    ));
+    engine.process(new_tx(
+        client_id,
+        2,
+        TransactionType::Deposit,
+        Some(dec!(50.0)),
+    ));
+    engine.process(new_tx(
+        client_id,
+        3,
+        TransactionType::Withdrawal,
+        Some(dec!(20.0)),
+    ));
+    engine.process(new_tx(client_id, 1, TransactionType::Dispute, None));
+    engine.process(new_tx(client_id, 1, TransactionType::Resolve, None));
+    engine.process(new_tx(client_id, 2, TransactionType::Dispute, None));
+    engine.process(new_tx(client_id, 2, TransactionType::Chargeback, None));
+
+    assert_eq!(engine.ledger().entries().len(), 7);
+    assert!(engine.check_ledger().is_ok());
+    assert_eq!(
+        engine.ledger().balance(LedgerAccount::ChargebackLosses),
+        dec!(50.0)
+    );
+    assert_eq!(
+        engine.ledger().balance(LedgerAccount::Settlement),
+        dec!(-130.0)
+    );
+}
+
+#[test]
+fn test_ledger_ignores_rejected_transactions() {
+    let mut engine = Engine::new();
+    engine.keep_journal();
+    let client_id = 1;
+
+    engine.process(new_tx(
+        client_id,
+        1,
+        TransactionType::Deposit,
+        Some(dec!(10.0)),
+    ));
+    engine.process(new_tx(
+        client_id,
+        2,
+        TransactionType::Withdrawal,
+        Some(dec!(20.0)),
+    ));
+    engine.process(new_tx(client_id, 2, TransactionType::Dispute, None));
+
+    assert_eq!(engine.ledger().entries().len(), 1);
+    assert!(engine.check_ledger().is_ok());
+}
+
+#[test]
+fn test_process_reports_rejection_reason() {
+    let mut engine = Engine::new();
+    let client_id = 1;
+
+    let applied = engine.process(new_tx(
+        client_id,
+        1,
+        TransactionType::Deposit,
+        Some(dec!(10.0)),
+    ));
+    let duplicate = engine.process(new_tx(
+        client_id,
+        1,
+        TransactionType::Deposit,
+        Some(dec!(10.0)),
+    ));
+    let insufficient = engine.process(new_tx(
+        client_id,
+        2,
+        TransactionType::Withdrawal,
+        Some(dec!(20.0)),
+    ));
+    engine.process(new_tx(
+        client_id,
+        3,
+        TransactionType::Withdrawal,
+        Some(dec!(5.0)),
+    ));
+    let unknown = engine.process(new_tx(client_id, 9, TransactionType::Dispute, None));
+    let not_a_deposit = engine.process(new_tx(client_id, 3, TransactionType::Resolve, None));
+    let mismatch = engine.process(new_tx(2, 1, TransactionType::Dispute, None));
+    let not_disputed = engine.process(new_tx(client_id, 1, TransactionType::Chargeback, None));
+
+    assert_eq!(applied, Outcome::Applied);
+    assert_eq!(duplicate, Outcome::Rejected(Rejection::Duplicate));
+    assert_eq!(
+        insufficient,
+        Outcome::Rejected(Rejection::InsufficientFunds)
+    );
+    assert_eq!(unknown, Outcome::Rejected(Rejection::UnknownTransaction));
+    assert_eq!(not_a_deposit, Outcome::Rejected(Rejection::NotADeposit));
+    assert_eq!(mismatch, Outcome::Rejected(Rejection::ClientMismatch));
+    assert_eq!(not_disputed, Outcome::Rejected(Rejection::NotDisputed));
+}
+
+#[test]
+fn test_history_records_balance_after_each_event() {
+    let mut engine = Engine::new();
+    engine.keep_history();
+    let client_id = 42;
+
+    engine.process(new_tx(
+        client_id,
+        1,
+        TransactionType::Deposit,
+        Some(dec!(100.0)),
+    ));
+    engine.process(new_tx(7, 2, TransactionType::Deposit, Some(dec!(5.0))));
+    engine.process(new_tx(
+        client_id,
+        3,
+        TransactionType::Withdrawal,
+        Some(dec!(500.0)),
+    ));
+    engine.process(new_tx(client_id, 1, TransactionType::Dispute, None));
+
+    let history = engine.history(client_id);
+    assert_eq!(history.len(), 3);
+
+    assert_eq!(history[0].seq, 0);
+    assert_eq!(history[0].outcome, Outcome::Applied);
+    assert_eq!(history[0].balance.available, dec!(100.0));
+
+    assert_eq!(history[1].seq, 2);
+    assert_eq!(
+        history[1].outcome,
+        Outcome::Rejected(Rejection::InsufficientFunds)
+    );
+    assert_eq!(history[1].balance.available, dec!(100.0));
+
+    assert_eq!(history[2].r#type, TransactionType::Dispute);
+    assert_eq!(history[2].balance.available, dec!(0.0));
+    assert_eq!(history[2].balance.held, dec!(100.0));
+}
+
+#[test]
+fn test_restored_engine_has_its_balances_before_the_history() {
+    let mut engine = Engine::new();
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(100.0))));
+    let mut snapshot = Vec::new();
+    engine.snapshot(&mut snapshot).unwrap();
+
+    let mut engine = Engine::restore(snapshot.as_slice()).unwrap();
+    engine.keep_history();
+    engine.process(new_tx(1, 2, TransactionType::Withdrawal, Some(dec!(40.0))));
+
+    let before = engine.balance_at(1, PointInTime::BeforeTx(2)).unwrap();
+    assert_eq!(before.available, dec!(100.0));
+    assert_eq!(
+        engine.balance_at(2, PointInTime::BeforeTx(2)),
+        Some(Balance::default())
+    );
+}
+
+#[test]
+fn test_history_of_unknown_client_is_empty() {
+    let engine = Engine::new();
+
+    assert!(engine.history(1).is_empty());
+}
+
+#[test]
+fn test_process_batch_applies_every_row() {
+    let mut engine = Engine::new();
+    engine.keep_history();
+    let client_id = 1;
+    engine.process(new_tx(
+        client_id,
+        1,
+        TransactionType::Deposit,
+        Some(dec!(100.0)),
+    ));
+
+    let result = engine.process_batch(vec![
+        new_tx(client_id, 2, TransactionType::Withdrawal, Some(dec!(60.0))),
+        new_tx(client_id, 3, TransactionType::Withdrawal, Some(dec!(1.5))),
+    ]);
+
+    assert!(result.is_ok());
+    let account = engine.get_accounts().get(&client_id).unwrap();
+    assert_eq!(account.available, dec!(38.5));
+    assert_eq!(engine.history(client_id).len(), 3);
+}
+
+#[test]
+fn test_process_batch_is_all_or_nothing() {
+    let mut engine = Engine::new();
+    engine.keep_journal();
+    engine.keep_history();
+    let client_id = 1;
+    engine.process(new_tx(
+        client_id,
+        1,
+        TransactionType::Deposit,
+        Some(dec!(100.0)),
+    ));
+
+    let result = engine.process_batch(vec![
+        new_tx(client_id, 2, TransactionType::Withdrawal, Some(dec!(99.0))),
+        new_tx(client_id, 3, TransactionType::Withdrawal, Some(dec!(1.5))),
+    ]);
+
+    assert_eq!(
+        result,
+        Err(BatchRejected {
+            index: 1,
+            rejection: Rejection::InsufficientFunds,
+        })
+    );
+    let account = engine.get_accounts().get(&client_id).unwrap();
+    assert_eq!(account.available, dec!(100.0));
+    assert_eq!(engine.history(client_id).len(), 1);
+    assert_eq!(engine.ledger().entries().len(), 1);
+}
+
+#[test]
+fn test_process_batch_sees_its_own_rows() {
+    let mut engine = Engine::new();
+    let client_id = 1;
+
+    let result = engine.process_batch(vec![
+        new_tx(client_id, 1, TransactionType::Deposit, Some(dec!(10.0))),
+        new_tx(client_id, 1, TransactionType::Dispute, None),
+        new_tx(client_id, 1, TransactionType::Deposit, Some(dec!(10.0))),
+    ]);
+
+    assert_eq!(
+        result,
+        Err(BatchRejected {
+            index: 2,
+            rejection: Rejection::Duplicate,
+        })
+    );
+    assert!(engine.get_accounts().is_empty());
+}
+
+#[test]
+fn test_accounts_iterates_in_client_order() {
+    let mut engine = Engine::new();
+    for (tx, client_id) in [(1, 300), (2, 7), (3, 42), (4, 1)] {
+        engine.process(new_tx(
+            client_id,
+            tx,
+            TransactionType::Deposit,
+            Some(dec!(1.0)),
+        ));
+    }
+
+    let client_ids: Vec<u16> = engine.accounts().map(|(client_id, _)| client_id).collect();
+
+    assert_eq!(client_ids, vec![1, 7, 42, 300]);
+}
+
+#[test]
+fn test_accounts_by_column_breaks_ties_by_client() {
+    let mut engine = Engine::new();
+    engine.process(new_tx(3, 1, TransactionType::Deposit, Some(dec!(5.0))));
+    engine.process(new_tx(2, 2, TransactionType::Deposit, Some(dec!(1.0))));
+    engine.process(new_tx(1, 3, TransactionType::Deposit, Some(dec!(5.0))));
+    engine.process(new_tx(1, 3, TransactionType::Dispute, None));
+    engine.process(new_tx(1, 3, TransactionType::Chargeback, None));
+
+    let by_total: Vec<u16> = engine
+        .accounts_by(AccountOrder::Total)
+        .into_iter()
+        .map(|(client_id, _)| client_id)
+        .collect();
+    let by_locked: Vec<u16> = engine
+        .accounts_by(AccountOrder::Locked)
+        .into_iter()
+        .map(|(client_id, _)| client_id)
+        .collect();
+
+    assert_eq!(by_total, vec![1, 2, 3]);
+    assert_eq!(by_locked, vec![2, 3, 1]);
+}
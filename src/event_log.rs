@@ -0,0 +1,276 @@
+use crate::Engine;
+use crate::codec::{
+    invalid_data, read_decimal, read_u8, read_u16, read_u32, read_u64, type_code, type_from_code,
+};
+use crate::models::TransactionInput;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+const MAGIC: &[u8; 4] = b"TXEL";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 6;
+// payload length + checksum
+const RECORD_PREFIX_LEN: usize = 8;
+
+// type + client + tx + amount flag + amount + timestamp flag + timestamp
+const MAX_PAYLOAD_LEN: u32 = 1 + 2 + 4 + 1 + 16 + 1 + 8;
+
+// When appended records are forced to disk. With `Record`, each one is before its row is
+// applied, so the log survives a power loss or an OS crash. With `Os`, records are only
+// handed to the OS, which survives a crash of the process and does not wait for the disk
+// on every row; they are forced to disk at checkpoints and at the end of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogSync {
+    #[default]
+    Record,
+    Os,
+}
+
+impl FromStr for LogSync {
+    type Err = String;
+
+    fn from_str(sync: &str) -> Result<Self, Self::Err> {
+        match sync {
+            "record" => Ok(LogSync::Record),
+            "os" => Ok(LogSync::Os),
+            _ => Err(format!("unknown event log sync `{sync}`")),
+        }
+    }
+}
+
+// What a log is written to. A file can be forced to disk; a buffer is done once written.
+pub trait LogWriter: Write {
+    fn sync_data(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl LogWriter for File {
+    fn sync_data(&self) -> io::Result<()> {
+        File::sync_data(self)
+    }
+}
+
+impl LogWriter for Vec<u8> {}
+
+pub struct EventLog<W: Write> {
+    writer: W,
+    sync: LogSync,
+}
+
+impl EventLog<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        if file.metadata()?.len() == 0 {
+            write_header(&mut file)?;
+        } else {
+            let mut reader = BufReader::new(&file);
+            read_header(&mut reader)?;
+
+            // A record torn by a crash is cut off here, or the next record appended after
+            // it would be read as its missing bytes and fail the replay.
+            let mut len = HEADER_LEN as u64;
+            while let Some(payload) = read_record(&mut reader)? {
+                len += RECORD_PREFIX_LEN as u64 + payload.len() as u64;
+            }
+            file.set_len(len)?;
+        }
+
+        Ok(Self {
+            writer: file,
+            sync: LogSync::default(),
+        })
+    }
+
+    // Drops every record past `len`, e.g. the ones appended after the checkpoint a run
+    // resumes from.
+    pub fn open_truncated<P: AsRef<Path>>(path: P, len: u64) -> io::Result<Self> {
+        OpenOptions::new().write(true).open(&path)?.set_len(len)?;
+        Self::open(path)
+    }
+
+    pub fn byte_len(&self) -> io::Result<u64> {
+        Ok(self.writer.metadata()?.len())
+    }
+}
+
+impl<W: LogWriter> EventLog<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        write_header(&mut writer)?;
+        Ok(Self {
+            writer,
+            sync: LogSync::default(),
+        })
+    }
+
+    pub fn set_sync(&mut self, sync: LogSync) {
+        self.sync = sync;
+    }
+
+    pub fn sync(&self) -> io::Result<()> {
+        self.writer.sync_data()
+    }
+
+    // Each record is written with a single call, so it reaches the OS (or, with
+    // `LogSync::Record`, the disk) before the caller applies the transaction to the engine.
+    pub fn append(&mut self, transaction: &TransactionInput) -> io::Result<()> {
+        let payload = encode(transaction);
+        let mut record = Vec::with_capacity(RECORD_PREFIX_LEN + payload.len());
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+
+        self.writer.write_all(&record)?;
+        self.writer.flush()?;
+        match self.sync {
+            LogSync::Record => self.writer.sync_data(),
+            LogSync::Os => Ok(()),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub fn replay<R: Read>(reader: R) -> io::Result<Engine> {
+    let mut engine = Engine::new();
+    replay_into(&mut engine, reader)?;
+    Ok(engine)
+}
+
+// A record cut short by a crash is the tail of the log and ends the replay; a record
+// whose checksum does not match is corruption and fails it.
+pub fn replay_into<R: Read>(engine: &mut Engine, mut reader: R) -> io::Result<u64> {
+    read_header(&mut reader)?;
+
+    let mut replayed = 0;
+    while let Some(payload) = read_record(&mut reader)? {
+        engine.process(decode(&payload)?);
+        replayed += 1;
+    }
+
+    Ok(replayed)
+}
+
+fn write_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.flush()
+}
+
+fn read_header<R: Read>(reader: &mut R) -> io::Result<()> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+
+    if &header[..4] != MAGIC {
+        return Err(invalid_data("not an event log"));
+    }
+
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "unsupported event log version {version}"
+        )));
+    }
+
+    Ok(())
+}
+
+fn read_record<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut prefix = [0u8; RECORD_PREFIX_LEN];
+    if !read_full(reader, &mut prefix)? {
+        return Ok(None);
+    }
+
+    let len = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]);
+    let checksum = u32::from_le_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]);
+    if len > MAX_PAYLOAD_LEN {
+        return Err(invalid_data(format!("invalid event record length {len}")));
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    if !read_full(reader, &mut payload)? {
+        return Ok(None);
+    }
+
+    if crc32fast::hash(&payload) != checksum {
+        return Err(invalid_data("event record checksum mismatch"));
+    }
+
+    Ok(Some(payload))
+}
+
+// Returns false when the reader ends before `buf` is filled.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => return Ok(false),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(true)
+}
+
+fn encode(transaction: &TransactionInput) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(MAX_PAYLOAD_LEN as usize);
+    payload.push(type_code(&transaction.r#type));
+    payload.extend_from_slice(&transaction.client.to_le_bytes());
+    payload.extend_from_slice(&transaction.tx.to_le_bytes());
+
+    match transaction.amount {
+        Some(amount) => {
+            payload.push(1);
+            payload.extend_from_slice(&amount.serialize());
+        }
+        None => payload.push(0),
+    }
+
+    match transaction.timestamp {
+        Some(timestamp) => {
+            payload.push(1);
+            payload.extend_from_slice(&timestamp.to_le_bytes());
+        }
+        None => payload.push(0),
+    }
+
+    payload
+}
+
+fn decode(mut payload: &[u8]) -> io::Result<TransactionInput> {
+    let r#type = type_from_code(read_u8(&mut payload)?)?;
+    let client = read_u16(&mut payload)?;
+    let tx = read_u32(&mut payload)?;
+    let amount = match read_u8(&mut payload)? {
+        0 => None,
+        1 => Some(read_decimal(&mut payload)?),
+        flag => return Err(invalid_data(format!("invalid amount flag {flag}"))),
+    };
+    let timestamp = match read_u8(&mut payload)? {
+        0 => None,
+        1 => Some(read_u64(&mut payload)?),
+        flag => return Err(invalid_data(format!("invalid timestamp flag {flag}"))),
+    };
+
+    Ok(TransactionInput {
+        r#type,
+        client,
+        tx,
+        amount,
+        timestamp,
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,180 @@
+use super::*;
+use crate::models::TransactionType;
+use crate::test_support::new_tx;
+use rust_decimal_macros::dec;
+use std::cell::Cell;
+
+fn write_log(transactions: Vec<TransactionInput>) -> Vec<u8> {
+    let mut log = EventLog::new(Vec::new()).unwrap();
+    for transaction in &transactions {
+        log.append(transaction).unwrap();
+    }
+    log.into_inner()
+}
+
+#[test]
+fn test_replay_rebuilds_engine() {
+    let bytes = write_log(vec![
+        new_tx(1, 1, TransactionType::Deposit, Some(dec!(100.1234))),
+        new_tx(1, 2, TransactionType::Withdrawal, Some(dec!(0.1234))),
+        new_tx(2, 3, TransactionType::Deposit, Some(dec!(50.0))),
+        new_tx(2, 3, TransactionType::Dispute, None),
+        new_tx(2, 3, TransactionType::Chargeback, None),
+    ]);
+
+    let engine = replay(bytes.as_slice()).unwrap();
+
+    let first = engine.get_accounts().get(&1).unwrap();
+    assert_eq!(first.available, dec!(100.0));
+    let second = engine.get_accounts().get(&2).unwrap();
+    assert_eq!(second.total(), dec!(0.0));
+    assert!(second.locked);
+}
+
+#[test]
+fn test_replay_into_counts_events() {
+    let bytes = write_log(vec![
+        new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))),
+        new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))),
+    ]);
+
+    let mut engine = Engine::new();
+    let replayed = replay_into(&mut engine, bytes.as_slice()).unwrap();
+
+    assert_eq!(replayed, 2);
+    assert_eq!(engine.get_accounts().get(&1).unwrap().available, dec!(10.0));
+}
+
+#[test]
+fn test_replay_stops_at_torn_tail() {
+    let mut bytes = write_log(vec![
+        new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))),
+        new_tx(1, 2, TransactionType::Deposit, Some(dec!(5.0))),
+    ]);
+    bytes.truncate(bytes.len() - 3);
+
+    let engine = replay(bytes.as_slice()).unwrap();
+
+    assert_eq!(engine.get_accounts().get(&1).unwrap().available, dec!(10.0));
+}
+
+#[test]
+fn test_replay_rejects_corrupted_record() {
+    let mut bytes = write_log(vec![new_tx(
+        1,
+        1,
+        TransactionType::Deposit,
+        Some(dec!(10.0)),
+    )]);
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xFF;
+
+    assert!(replay(bytes.as_slice()).is_err());
+}
+
+#[test]
+fn test_replay_rejects_unknown_format() {
+    assert!(replay(&b"TXES\x01\x00"[..]).is_err());
+}
+
+#[test]
+fn test_open_appends_to_existing_log() {
+    let path = std::env::temp_dir().join(format!("event_log_test_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut log = EventLog::open(&path).unwrap();
+    log.append(&new_tx(1, 1, TransactionType::Deposit, Some(dec!(1.0))))
+        .unwrap();
+    drop(log);
+
+    let mut log = EventLog::open(&path).unwrap();
+    log.append(&new_tx(1, 2, TransactionType::Deposit, Some(dec!(2.0))))
+        .unwrap();
+    log.sync().unwrap();
+    drop(log);
+
+    let engine = replay(File::open(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(engine.get_accounts().get(&1).unwrap().available, dec!(3.0));
+}
+
+#[test]
+fn test_open_cuts_off_torn_tail() {
+    let path = std::env::temp_dir().join(format!("event_log_torn_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut log = EventLog::open(&path).unwrap();
+    log.append(&new_tx(1, 1, TransactionType::Deposit, Some(dec!(1.0))))
+        .unwrap();
+    log.append(&new_tx(1, 2, TransactionType::Deposit, Some(dec!(2.0))))
+        .unwrap();
+    let len = log.byte_len().unwrap();
+    drop(log);
+    OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_len(len - 3)
+        .unwrap();
+
+    let mut log = EventLog::open(&path).unwrap();
+    log.append(&new_tx(1, 3, TransactionType::Deposit, Some(dec!(4.0))))
+        .unwrap();
+    drop(log);
+
+    let engine = replay(File::open(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(engine.get_accounts().get(&1).unwrap().available, dec!(5.0));
+}
+
+#[test]
+fn test_replay_keeps_timestamps() {
+    let mut transaction = new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0)));
+    transaction.timestamp = Some(1_700_000_000);
+    let bytes = write_log(vec![transaction]);
+
+    let mut engine = Engine::new();
+    engine.keep_history();
+    replay_into(&mut engine, bytes.as_slice()).unwrap();
+
+    assert_eq!(engine.history(1)[0].timestamp, Some(1_700_000_000));
+}
+
+#[derive(Default)]
+struct SyncCounter {
+    bytes: Vec<u8>,
+    syncs: Cell<u32>,
+}
+
+impl Write for SyncCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl LogWriter for SyncCounter {
+    fn sync_data(&self) -> io::Result<()> {
+        self.syncs.set(self.syncs.get() + 1);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_append_syncs_each_record_unless_left_to_the_os() {
+    let mut log = EventLog::new(SyncCounter::default()).unwrap();
+    log.append(&new_tx(1, 1, TransactionType::Deposit, Some(dec!(1.0))))
+        .unwrap();
+    log.append(&new_tx(1, 2, TransactionType::Deposit, Some(dec!(2.0))))
+        .unwrap();
+    log.set_sync(LogSync::Os);
+    log.append(&new_tx(1, 3, TransactionType::Deposit, Some(dec!(3.0))))
+        .unwrap();
+
+    assert_eq!(log.into_inner().syncs.get(), 2);
+}
@@ -0,0 +1,113 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+// How long to wait at the end of the file before looking for new data again.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Reads a file that is still being appended to, like `tail -f`: at its end it waits for
+// more data instead of returning EOF, until the stop flag is set. Only whole lines are passed
+// on, so a row that is half written is never parsed.
+pub struct FollowReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    // `buffer[read..complete]` holds whole lines not returned yet; after it comes the start of
+    // an unfinished line.
+    read: usize,
+    complete: usize,
+    stop: Arc<AtomicBool>,
+}
+
+impl<R> FollowReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            read: 0,
+            complete: 0,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Once the flag is set, the reader ends at the current end of the file.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+}
+
+impl<R: Read> FollowReader<R> {
+    // Waits for at least one more whole line; false once stopped without one.
+    fn fill(&mut self) -> io::Result<bool> {
+        self.buffer.drain(..self.complete);
+        self.read = 0;
+        self.complete = 0;
+
+        let mut chunk = [0; 8192];
+        loop {
+            let n = match self.inner.read(&mut chunk) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                if self.stop.load(Ordering::Relaxed) {
+                    if !self.buffer.is_empty() {
+                        warn!(
+                            bytes = self.buffer.len(),
+                            "stopping before an unfinished last line"
+                        );
+                        self.buffer.clear();
+                    }
+                    return Ok(false);
+                }
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            let start = self.buffer.len();
+            self.buffer.extend_from_slice(&chunk[..n]);
+            if let Some(end) = chunk[..n].iter().rposition(|&byte| byte == b'\n') {
+                self.complete = start + end + 1;
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for FollowReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.read == self.complete && !self.fill()? {
+            return Ok(0);
+        }
+
+        let n = buf.len().min(self.complete - self.read);
+        buf[..n].copy_from_slice(&self.buffer[self.read..self.read + n]);
+        self.read += n;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for FollowReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        // Relative to what was returned, not to what was buffered ahead of it.
+        let pos = match pos {
+            SeekFrom::Current(offset) => {
+                SeekFrom::Current(offset - (self.buffer.len() - self.read) as i64)
+            }
+            pos => pos,
+        };
+        self.buffer.clear();
+        self.read = 0;
+        self.complete = 0;
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,76 @@
+use super::*;
+use crate::input::{InputFormat, TransactionReader};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Cursor, Write};
+
+// Returns its chunks one read at a time, then nothing.
+struct Chunks(VecDeque<&'static [u8]>);
+
+impl Read for Chunks {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(chunk) = self.0.pop_front() else {
+            return Ok(0);
+        };
+        buf[..chunk.len()].copy_from_slice(chunk);
+        Ok(chunk.len())
+    }
+}
+
+#[test]
+fn test_passes_on_whole_lines_only() {
+    let reader = FollowReader::new(Chunks(VecDeque::from([
+        &b"deposit,1,1,"[..],
+        b"1.0\ndep",
+        b"osit,1,2,2.0\nwithdr",
+    ])));
+    reader.stop_flag().store(true, Ordering::Relaxed);
+
+    let mut data = String::new();
+    BufReader::new(reader).read_to_string(&mut data).unwrap();
+
+    assert_eq!(data, "deposit,1,1,1.0\ndeposit,1,2,2.0\n");
+}
+
+#[test]
+fn test_waits_for_appended_rows() {
+    let path = std::env::temp_dir().join(format!("follow-{}.csv", std::process::id()));
+    fs::write(&path, "type,client,tx,amount\ndeposit,1,1,1.0\n").unwrap();
+
+    let reader = FollowReader::new(File::open(&path).unwrap());
+    let stop = reader.stop_flag();
+    let appender = {
+        let path = path.clone();
+        thread::spawn(move || {
+            thread::sleep(POLL_INTERVAL * 2);
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(b"deposit,1,2,").unwrap();
+            file.flush().unwrap();
+            thread::sleep(POLL_INTERVAL * 2);
+            file.write_all(b"2.5\n").unwrap();
+            stop.store(true, Ordering::Relaxed);
+        })
+    };
+
+    let rows: TransactionReader<_> = TransactionReader::new(reader, InputFormat::Csv).unwrap();
+    let txs: Vec<u32> = rows.map(|row| row.unwrap().tx).collect();
+    appender.join().unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(txs, vec![1, 2]);
+}
+
+#[test]
+fn test_seek_drops_buffered_data() {
+    let mut reader = FollowReader::new(Cursor::new(b"a\nb\nc\n".to_vec()));
+    reader.stop_flag().store(true, Ordering::Relaxed);
+
+    let mut first = [0; 2];
+    reader.read_exact(&mut first).unwrap();
+    assert_eq!(reader.stream_position().unwrap(), 2);
+    reader.seek(SeekFrom::Start(4)).unwrap();
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+
+    assert_eq!(rest, "c\n");
+}
@@ -0,0 +1,128 @@
+use crate::models::{Balance, HistoryOutput, Outcome, TransactionInput, TransactionType};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub seq: u64,
+    pub tx: u32,
+    pub r#type: TransactionType,
+    pub amount: Option<Decimal>,
+    pub timestamp: Option<u64>,
+    pub outcome: Outcome,
+    pub balance: Balance,
+}
+
+impl HistoryEntry {
+    pub fn to_output(&self) -> HistoryOutput {
+        HistoryOutput {
+            seq: self.seq,
+            tx: self.tx,
+            r#type: self.r#type.as_str(),
+            amount: self.amount,
+            outcome: self.outcome.as_str(),
+            available: self.balance.available.round_dp(4),
+            held: self.balance.held.round_dp(4),
+            total: self.balance.total().round_dp(4),
+            locked: self.balance.locked,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointInTime {
+    // Right before the first event that refers to this tx id, whichever client it was for.
+    BeforeTx(u32),
+    // Right after the event with this sequence number, inclusive.
+    Seq(u64),
+    // Right before the first event stamped later than this timestamp.
+    Timestamp(u64),
+}
+
+impl fmt::Display for PointInTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointInTime::BeforeTx(tx) => write!(f, "right before tx {tx}"),
+            PointInTime::Seq(seq) => write!(f, "as of seq {seq}"),
+            PointInTime::Timestamp(timestamp) => write!(f, "as of timestamp {timestamp}"),
+        }
+    }
+}
+
+// Entries carry the engine's global sequence number, so entries of different clients can
+// still be put back in processing order.
+#[derive(Default)]
+pub(crate) struct History {
+    entries: HashMap<u16, Vec<HistoryEntry>>,
+    first_seq_of_tx: HashMap<u32, u64>,
+    // Balances of the clients that had an account when recording began, e.g. from a
+    // loaded state; a point before a client's first entry has this balance.
+    starting: HashMap<u16, Balance>,
+}
+
+impl History {
+    pub(crate) fn starting_from(starting: HashMap<u16, Balance>) -> Self {
+        Self {
+            starting,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        seq: u64,
+        transaction: &TransactionInput,
+        outcome: Outcome,
+        balance: Balance,
+    ) {
+        self.first_seq_of_tx.entry(transaction.tx).or_insert(seq);
+        let entry = HistoryEntry {
+            seq,
+            tx: transaction.tx,
+            r#type: transaction.r#type,
+            amount: transaction.amount,
+            timestamp: transaction.timestamp,
+            outcome,
+            balance,
+        };
+        self.entries
+            .entry(transaction.client)
+            .or_default()
+            .push(entry);
+    }
+
+    pub(crate) fn for_client(&self, client_id: u16) -> &[HistoryEntry] {
+        self.entries
+            .get(&client_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    // None when the point refers to a tx no event was ever for.
+    pub(crate) fn balance_at(&self, client_id: u16, at: PointInTime) -> Option<Balance> {
+        let entries = self.for_client(client_id);
+
+        let end = match at {
+            PointInTime::BeforeTx(tx) => {
+                let seq = *self.first_seq_of_tx.get(&tx)?;
+                entries.partition_point(|entry| entry.seq < seq)
+            }
+            PointInTime::Seq(seq) => entries.partition_point(|entry| entry.seq <= seq),
+            PointInTime::Timestamp(timestamp) => entries
+                .iter()
+                .position(|entry| entry.timestamp.is_some_and(|stamp| stamp > timestamp))
+                .unwrap_or(entries.len()),
+        };
+
+        let balance = match end.checked_sub(1) {
+            Some(last) => entries[last].balance,
+            None => self.starting.get(&client_id).copied().unwrap_or_default(),
+        };
+
+        Some(balance)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,154 @@
+use super::*;
+use crate::models::Rejection;
+use crate::test_support::new_tx;
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_record_keeps_entries_per_client() {
+    let mut history = History::default();
+
+    history.record(
+        0,
+        &new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))),
+        Outcome::Applied,
+        Balance::default(),
+    );
+    history.record(
+        1,
+        &new_tx(2, 2, TransactionType::Deposit, Some(dec!(10.0))),
+        Outcome::Applied,
+        Balance::default(),
+    );
+    history.record(
+        2,
+        &new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))),
+        Outcome::Rejected(Rejection::Duplicate),
+        Balance::default(),
+    );
+
+    let seqs: Vec<u64> = history
+        .for_client(1)
+        .iter()
+        .map(|entry| entry.seq)
+        .collect();
+    assert_eq!(seqs, vec![0, 2]);
+    assert_eq!(history.for_client(2)[0].seq, 1);
+}
+
+#[test]
+fn test_for_unknown_client_is_empty() {
+    let history = History::default();
+
+    assert!(history.for_client(42).is_empty());
+}
+
+#[test]
+fn test_entry_output_rounds_balance() {
+    let entry = HistoryEntry {
+        seq: 0,
+        tx: 1,
+        r#type: TransactionType::Deposit,
+        amount: Some(dec!(1.23456)),
+        timestamp: None,
+        outcome: Outcome::Applied,
+        balance: Balance {
+            available: dec!(1.23456),
+            held: dec!(0.0),
+            locked: false,
+        },
+    };
+
+    let output = entry.to_output();
+
+    assert_eq!(output.r#type, "deposit");
+    assert_eq!(output.outcome, "applied");
+    assert_eq!(output.available, dec!(1.2346));
+    assert_eq!(output.total, dec!(1.2346));
+}
+
+fn history_with_timestamps() -> History {
+    let mut history = History::default();
+    let mut balance = Balance::default();
+
+    for (seq, tx, timestamp, available) in [
+        (0, 1, 100, dec!(10.0)),
+        (1, 2, 200, dec!(25.0)),
+        (2, 3, 300, dec!(5.0)),
+    ] {
+        balance.available = available;
+        let mut transaction = new_tx(7, tx, TransactionType::Deposit, Some(dec!(10.0)));
+        transaction.timestamp = Some(timestamp);
+        history.record(seq, &transaction, Outcome::Applied, balance);
+    }
+
+    history
+}
+
+#[test]
+fn test_balance_before_tx() {
+    let history = history_with_timestamps();
+
+    let before_first = history.balance_at(7, PointInTime::BeforeTx(1)).unwrap();
+    let before_third = history.balance_at(7, PointInTime::BeforeTx(3)).unwrap();
+
+    assert_eq!(before_first, Balance::default());
+    assert_eq!(before_third.available, dec!(25.0));
+    assert_eq!(history.balance_at(7, PointInTime::BeforeTx(99)), None);
+}
+
+#[test]
+fn test_balance_before_tx_of_another_client() {
+    let mut history = history_with_timestamps();
+    history.record(
+        3,
+        &new_tx(8, 4, TransactionType::Deposit, Some(dec!(10.0))),
+        Outcome::Applied,
+        Balance::default(),
+    );
+    let balance = Balance {
+        available: dec!(1.0),
+        ..Balance::default()
+    };
+    history.record(
+        4,
+        &new_tx(7, 5, TransactionType::Deposit, Some(dec!(10.0))),
+        Outcome::Applied,
+        balance,
+    );
+
+    let before = history.balance_at(7, PointInTime::BeforeTx(4)).unwrap();
+    let unknown_client = history.balance_at(9, PointInTime::BeforeTx(4)).unwrap();
+
+    assert_eq!(before.available, dec!(5.0));
+    assert_eq!(unknown_client, Balance::default());
+}
+
+#[test]
+fn test_point_in_time_display() {
+    assert_eq!(PointInTime::BeforeTx(9).to_string(), "right before tx 9");
+    assert_eq!(PointInTime::Seq(3).to_string(), "as of seq 3");
+}
+
+#[test]
+fn test_balance_as_of_seq() {
+    let history = history_with_timestamps();
+
+    let balance = history.balance_at(7, PointInTime::Seq(1)).unwrap();
+
+    assert_eq!(balance.available, dec!(25.0));
+}
+
+#[test]
+fn test_balance_as_of_timestamp() {
+    let history = history_with_timestamps();
+
+    let early = history.balance_at(7, PointInTime::Timestamp(50)).unwrap();
+    let between = history.balance_at(7, PointInTime::Timestamp(250)).unwrap();
+    let late = history
+        .balance_at(7, PointInTime::Timestamp(1_000))
+        .unwrap();
+
+    assert_eq!(early, Balance::default());
+    assert_eq!(between.available, dec!(25.0));
+    assert_eq!(late.available, dec!(5.0));
+}
@@ -0,0 +1,396 @@
+use crate::Engine;
+use crate::metrics::Metrics;
+use crate::models::TransactionInput;
+use crate::output::{OutputFormat, OutputWriter};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Request};
+use tracing::{debug, warn};
+
+// Large enough for batches of tens of thousands of rows.
+const MAX_BODY: u64 = 16 * 1024 * 1024;
+
+// Threads reading request bodies; that many clients slow to send theirs hold up the rest.
+const READERS: usize = 4;
+
+// How often the request loop looks at the shutdown flag while no request comes in.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) content_type: &'static str,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: impl fmt::Display) -> Self {
+        #[derive(Serialize)]
+        struct Error {
+            error: String,
+        }
+
+        Self::json(
+            status,
+            &Error {
+                error: message.to_string(),
+            },
+        )
+    }
+
+    fn rows<T: Serialize>(format: OutputFormat, rows: impl IntoIterator<Item = T>) -> Self {
+        match write_rows(format, rows) {
+            Ok(body) => Self {
+                status: 200,
+                content_type: match format {
+                    OutputFormat::Csv => "text/csv",
+                    _ => "application/json",
+                },
+                body,
+            },
+            Err(e) => Self::error(500, e),
+        }
+    }
+}
+
+fn write_rows<T: Serialize>(
+    format: OutputFormat,
+    rows: impl IntoIterator<Item = T>,
+) -> io::Result<Vec<u8>> {
+    let mut writer = OutputWriter::new(Vec::new(), format);
+    for row in rows {
+        writer.write(&row)?;
+    }
+    writer.finish()
+}
+
+#[derive(Serialize)]
+struct Processed {
+    tx: u32,
+    outcome: &'static str,
+}
+
+#[derive(Serialize)]
+struct BatchApplied {
+    applied: usize,
+}
+
+#[derive(Serialize)]
+struct BatchFailed {
+    error: String,
+    index: usize,
+    outcome: &'static str,
+}
+
+// Answers one request against the engine. `authorized` tells whether the request carried the
+// admin token.
+pub(crate) fn route(
+    engine: &mut Engine,
+    metrics: Option<&Metrics>,
+    method: &str,
+    url: &str,
+    authorized: bool,
+    body: &[u8],
+) -> Response {
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    match (method, segments.as_slice()) {
+        ("POST", ["transactions"]) => {
+            let transaction: TransactionInput = match serde_json::from_slice(body) {
+                Ok(transaction) => transaction,
+                Err(e) => return Response::error(400, e),
+            };
+            let tx = transaction.tx;
+            let outcome = match metrics {
+                Some(metrics) => metrics.time(|| engine.process(transaction)),
+                None => engine.process(transaction),
+            };
+            Response::json(
+                200,
+                &Processed {
+                    tx,
+                    outcome: outcome.as_str(),
+                },
+            )
+        }
+        ("POST", ["batches"]) => {
+            let batch: Vec<TransactionInput> = match serde_json::from_slice(body) {
+                Ok(batch) => batch,
+                Err(e) => return Response::error(400, e),
+            };
+            let applied = batch.len();
+            match engine.process_batch(batch) {
+                Ok(()) => Response::json(200, &BatchApplied { applied }),
+                Err(rejected) => Response::json(
+                    409,
+                    &BatchFailed {
+                        error: rejected.to_string(),
+                        index: rejected.index,
+                        outcome: rejected.rejection.as_str(),
+                    },
+                ),
+            }
+        }
+        ("GET", ["accounts", client]) => {
+            let Ok(client) = client.parse::<u16>() else {
+                return Response::error(400, format!("invalid client id `{client}`"));
+            };
+            let Some(account) = engine.get_accounts().get(&client) else {
+                return Response::error(404, format!("client {client} has no account"));
+            };
+            let mut body = Vec::new();
+            match serde_json::to_writer(&mut body, &account.to_output(client)) {
+                Ok(()) => Response {
+                    status: 200,
+                    content_type: "application/json",
+                    body,
+                },
+                Err(e) => Response::error(500, e),
+            }
+        }
+        ("GET", ["accounts", client, "history"]) => {
+            let Ok(client) = client.parse::<u16>() else {
+                return Response::error(400, format!("invalid client id `{client}`"));
+            };
+            if !engine.keeps_history() {
+                return Response::error(404, "the history is not kept");
+            }
+            Response::rows(
+                OutputFormat::Json,
+                engine.history(client).iter().map(|entry| entry.to_output()),
+            )
+        }
+        ("GET", ["disputes"]) => Response::rows(OutputFormat::Json, engine.open_disputes()),
+        ("GET", ["admin", "accounts.csv"]) if !authorized => {
+            Response::error(401, "a valid admin token is required")
+        }
+        ("GET", ["admin", "accounts.csv"]) => Response::rows(
+            OutputFormat::Csv,
+            engine
+                .accounts()
+                .map(|(client, account)| account.to_output(client)),
+        ),
+        (
+            _,
+            ["transactions"]
+            | ["batches"]
+            | ["accounts", _]
+            | ["accounts", _, "history"]
+            | ["disputes"]
+            | ["admin", "accounts.csv"],
+        ) => Response::error(405, format!("{method} is not allowed on {path}")),
+        _ => Response::error(404, format!("no route for {path}")),
+    }
+}
+
+// A local HTTP API over one engine. Requests are answered one at a time on the thread that
+// calls `run`, so the engine needs no locking. Their bodies are read beforehand by a few
+// reader threads, so a client that is slow to send one does not hold up the engine.
+pub struct HttpApi {
+    server: tiny_http::Server,
+    admin_token: Option<String>,
+    metrics: Option<Metrics>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl HttpApi {
+    // The admin endpoints need an `Authorization: Bearer <token>` header with `admin_token`,
+    // and are refused to everyone without one.
+    pub fn bind<A: ToSocketAddrs>(addr: A, admin_token: Option<String>) -> io::Result<Self> {
+        Ok(Self {
+            server: tiny_http::Server::http(addr).map_err(io::Error::other)?,
+            admin_token,
+            metrics: None,
+            shutdown: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    // Times every transaction processed for `POST /transactions` in these metrics.
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = Some(metrics);
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| io::Error::other("not listening on an IP address"))
+    }
+
+    // Setting the flag makes `run` return once the request in progress is answered.
+    pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.shutdown)
+    }
+
+    pub fn run(&self, engine: &mut Engine) -> io::Result<()> {
+        let (queue, queued) = mpsc::channel();
+        let (read, requests) = mpsc::channel();
+        let queued = Arc::new(Mutex::new(queued));
+        // Not joined: a reader stuck on a client that stopped sending must not hold up the
+        // shutdown. Each one ends once the queue is closed and it finished its body.
+        for _ in 0..READERS {
+            let queued = Arc::clone(&queued);
+            let read = read.clone();
+            thread::spawn(move || read_bodies(&queued, &read));
+        }
+        drop(read);
+
+        thread::scope(|scope| {
+            let accept = scope.spawn(move || self.accept(queue));
+
+            let mut connections: HashMap<Option<SocketAddr>, Connection> = HashMap::new();
+            while !self.shutdown.load(Ordering::Relaxed) {
+                let (queued, body) = match requests.recv_timeout(POLL_INTERVAL) {
+                    Ok(read) => read,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                // tiny_http writes the responses of a connection in the order of its requests,
+                // so answering a later one first would wait for an answer that never comes.
+                let connection = connections.entry(queued.connection).or_default();
+                connection
+                    .waiting
+                    .insert(queued.seq, (queued.request, body));
+                while let Some((request, body)) = connection.waiting.remove(&connection.next) {
+                    connection.next += 1;
+                    if let Err(e) = self.respond(engine, request, body) {
+                        warn!(error = %e, "failed to answer a request");
+                    }
+                }
+            }
+
+            self.shutdown.store(true, Ordering::Relaxed);
+            accept
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+
+    // Numbers the requests of each connection, so they are answered in order however long
+    // their bodies take to read.
+    fn accept(&self, queue: Sender<Queued>) -> io::Result<()> {
+        let mut next_seqs: HashMap<Option<SocketAddr>, u64> = HashMap::new();
+        while !self.shutdown.load(Ordering::Relaxed) {
+            let Some(request) = self.server.recv_timeout(POLL_INTERVAL)? else {
+                continue;
+            };
+            let connection = request.remote_addr().copied();
+            let next_seq = next_seqs.entry(connection).or_default();
+            let _ = queue.send(Queued {
+                connection,
+                seq: *next_seq,
+                request,
+            });
+            *next_seq += 1;
+        }
+
+        Ok(())
+    }
+
+    fn respond(
+        &self,
+        engine: &mut Engine,
+        request: Request,
+        body: io::Result<Vec<u8>>,
+    ) -> io::Result<()> {
+        let body = body?;
+
+        let response = if body.len() as u64 > MAX_BODY {
+            Response::error(413, format!("the body is larger than {MAX_BODY} bytes"))
+        } else {
+            let authorized = self.is_authorized(&request);
+            route(
+                engine,
+                self.metrics.as_ref(),
+                request.method().as_str(),
+                request.url(),
+                authorized,
+                &body,
+            )
+        };
+        debug!(
+            method = %request.method(),
+            url = request.url(),
+            status = response.status,
+            "request answered"
+        );
+
+        let content_type = Header::from_bytes("Content-Type", response.content_type)
+            .map_err(|()| io::Error::other("invalid header"))?;
+        request.respond(
+            tiny_http::Response::from_data(response.body)
+                .with_status_code(response.status)
+                .with_header(content_type),
+        )
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        let Some(token) = &self.admin_token else {
+            return false;
+        };
+        let expected = format!("Bearer {token}");
+
+        request.headers().iter().any(|header| {
+            header.field.equiv("Authorization")
+                && constant_time_eq(header.value.as_str().as_bytes(), expected.as_bytes())
+        })
+    }
+}
+
+// Takes as long wherever the first difference is, so the time of an answer does not tell
+// how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+struct Queued {
+    connection: Option<SocketAddr>,
+    seq: u64,
+    request: Request,
+}
+
+// Requests of a connection that are read but wait for an earlier one, by their `seq`.
+#[derive(Default)]
+struct Connection {
+    next: u64,
+    waiting: HashMap<u64, (Request, io::Result<Vec<u8>>)>,
+}
+
+fn read_bodies(queued: &Mutex<Receiver<Queued>>, read: &Sender<(Queued, io::Result<Vec<u8>>)>) {
+    loop {
+        let next = queued.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let Ok(mut queued) = next else {
+            return;
+        };
+        let mut body = Vec::new();
+        let result = queued
+            .request
+            .as_reader()
+            .take(MAX_BODY + 1)
+            .read_to_end(&mut body)
+            .map(|_| body);
+        if read.send((queued, result)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,337 @@
+use super::*;
+use std::io::Write;
+use std::net::TcpStream;
+use std::thread;
+
+fn request(engine: &mut Engine, method: &str, url: &str, body: &str) -> (u16, String) {
+    let response = route(engine, None, method, url, true, body.as_bytes());
+    (response.status, String::from_utf8(response.body).unwrap())
+}
+
+fn deposit(engine: &mut Engine, client: u16, tx: u32, amount: &str) {
+    let body =
+        format!("{{\"type\":\"deposit\",\"client\":{client},\"tx\":{tx},\"amount\":\"{amount}\"}}");
+    assert_eq!(request(engine, "POST", "/transactions", &body).0, 200);
+}
+
+#[test]
+fn test_post_transaction_reports_outcome() {
+    let mut engine = Engine::new();
+
+    let applied = request(
+        &mut engine,
+        "POST",
+        "/transactions",
+        "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":\"2.5\"}",
+    );
+    let rejected = request(
+        &mut engine,
+        "POST",
+        "/transactions",
+        "{\"type\":\"withdrawal\",\"client\":1,\"tx\":2,\"amount\":\"9\"}",
+    );
+
+    assert_eq!(
+        applied,
+        (200, String::from("{\"tx\":1,\"outcome\":\"applied\"}"))
+    );
+    assert_eq!(
+        rejected,
+        (
+            200,
+            String::from("{\"tx\":2,\"outcome\":\"insufficient_funds\"}")
+        )
+    );
+}
+
+#[test]
+fn test_post_invalid_transaction_is_bad_request() {
+    let mut engine = Engine::new();
+
+    let (status, body) = request(&mut engine, "POST", "/transactions", "{\"type\":\"bogus\"}");
+
+    assert_eq!(status, 400);
+    assert!(body.starts_with("{\"error\":"));
+}
+
+#[test]
+fn test_batch_is_all_or_nothing() {
+    let mut engine = Engine::new();
+
+    let rejected = request(
+        &mut engine,
+        "POST",
+        "/batches",
+        "[{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":\"1\"},\
+          {\"type\":\"withdrawal\",\"client\":1,\"tx\":2,\"amount\":\"5\"}]",
+    );
+    assert_eq!(rejected.0, 409);
+    assert!(
+        rejected
+            .1
+            .contains("\"index\":1,\"outcome\":\"insufficient_funds\"")
+    );
+    assert!(engine.get_accounts().is_empty());
+
+    let applied = request(
+        &mut engine,
+        "POST",
+        "/batches",
+        "[{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":\"1\"},\
+          {\"type\":\"deposit\",\"client\":2,\"tx\":2,\"amount\":\"5\"}]",
+    );
+    assert_eq!(applied, (200, String::from("{\"applied\":2}")));
+    assert_eq!(engine.get_accounts().len(), 2);
+}
+
+#[test]
+fn test_post_transaction_is_timed() {
+    let mut engine = Engine::new();
+    let metrics = Metrics::new();
+
+    route(
+        &mut engine,
+        Some(&metrics),
+        "POST",
+        "/transactions",
+        true,
+        b"{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":\"2\"}",
+    );
+
+    assert!(
+        metrics
+            .render()
+            .contains("transaction_engine_processing_seconds_count 1\n")
+    );
+}
+
+#[test]
+fn test_get_account_and_history() {
+    let mut engine = Engine::new();
+    engine.keep_history();
+    deposit(&mut engine, 3, 1, "4.5");
+
+    assert_eq!(
+        request(&mut engine, "GET", "/accounts/3", ""),
+        (
+            200,
+            String::from(
+                "{\"client\":3,\"available\":\"4.5\",\"held\":\"0.0\",\"total\":\"4.5\",\"locked\":false}"
+            )
+        )
+    );
+    assert_eq!(request(&mut engine, "GET", "/accounts/4", "").0, 404);
+    assert_eq!(request(&mut engine, "GET", "/accounts/x", "").0, 400);
+
+    let (status, history) = request(&mut engine, "GET", "/accounts/3/history", "");
+    assert_eq!(status, 200);
+    assert!(history.starts_with("[\n{\"seq\":0,\"tx\":1,\"type\":\"deposit\""));
+}
+
+#[test]
+fn test_get_history_needs_it_kept() {
+    let mut engine = Engine::new();
+    deposit(&mut engine, 3, 1, "4.5");
+
+    assert_eq!(
+        request(&mut engine, "GET", "/accounts/3/history", "").0,
+        404
+    );
+}
+
+#[test]
+fn test_get_open_disputes() {
+    let mut engine = Engine::new();
+    deposit(&mut engine, 1, 7, "2");
+    request(
+        &mut engine,
+        "POST",
+        "/transactions",
+        "{\"type\":\"dispute\",\"client\":1,\"tx\":7}",
+    );
+
+    assert_eq!(
+        request(&mut engine, "GET", "/disputes", ""),
+        (
+            200,
+            String::from("[\n{\"tx\":7,\"client\":1,\"amount\":\"2.0\"}\n]\n")
+        )
+    );
+}
+
+#[test]
+fn test_admin_accounts_csv_needs_authorization() {
+    let mut engine = Engine::new();
+    deposit(&mut engine, 1, 1, "1.5");
+
+    let denied = route(&mut engine, None, "GET", "/admin/accounts.csv", false, b"");
+    let (status, csv) = request(&mut engine, "GET", "/admin/accounts.csv", "");
+
+    assert_eq!(denied.status, 401);
+    assert_eq!(status, 200);
+    assert_eq!(
+        csv,
+        "client,available,held,total,locked\n1,1.5,0.0,1.5,false\n"
+    );
+}
+
+#[test]
+fn test_unknown_routes_and_methods() {
+    let mut engine = Engine::new();
+
+    assert_eq!(request(&mut engine, "GET", "/transactions", "").0, 405);
+    assert_eq!(request(&mut engine, "DELETE", "/accounts/1", "").0, 405);
+    assert_eq!(request(&mut engine, "GET", "/nope", "").0, 404);
+}
+
+#[test]
+fn test_serves_requests_over_http() {
+    let api = HttpApi::bind("127.0.0.1:0", Some(String::from("secret"))).unwrap();
+    let addr = api.local_addr().unwrap();
+    let shutdown = api.shutdown_flag();
+    let server = thread::spawn(move || {
+        let mut engine = Engine::new();
+        api.run(&mut engine).unwrap();
+        engine
+    });
+
+    let body = "{\"type\":\"deposit\",\"client\":5,\"tx\":1,\"amount\":\"3\"}";
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "POST /transactions HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET /admin/accounts.csv HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+    let mut csv = String::new();
+    stream.read_to_string(&mut csv).unwrap();
+
+    shutdown.store(true, Ordering::Relaxed);
+    let engine = server.join().unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.ends_with("{\"tx\":1,\"outcome\":\"applied\"}"));
+    assert!(csv.contains("text/csv"));
+    assert!(csv.ends_with("5,3.0,0.0,3.0,false\n"));
+    assert_eq!(engine.get_accounts().len(), 1);
+}
+
+#[test]
+fn test_slow_body_does_not_hold_up_other_requests() {
+    let api = HttpApi::bind("127.0.0.1:0", None).unwrap();
+    let addr = api.local_addr().unwrap();
+    let shutdown = api.shutdown_flag();
+    let server = thread::spawn(move || {
+        let mut engine = Engine::new();
+        api.run(&mut engine).unwrap();
+    });
+
+    let mut slow = TcpStream::connect(addr).unwrap();
+    write!(
+        slow,
+        "POST /transactions HTTP/1.1\r\nHost: localhost\r\nContent-Length: 100000\r\n\r\n{{"
+    )
+    .unwrap();
+    // Lets the engine thread take the slow request first.
+    thread::sleep(Duration::from_millis(200));
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    write!(
+        stream,
+        "GET /disputes HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    shutdown.store(true, Ordering::Relaxed);
+    server.join().unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+}
+
+#[test]
+fn test_answers_pipelined_requests_in_order() {
+    let api = HttpApi::bind("127.0.0.1:0", None).unwrap();
+    let addr = api.local_addr().unwrap();
+    let shutdown = api.shutdown_flag();
+    let server = thread::spawn(move || {
+        let mut engine = Engine::new();
+        api.run(&mut engine).unwrap();
+        engine
+    });
+
+    // Every deposit is needed for the withdrawal sent last on the same connection.
+    let mut requests = String::new();
+    for tx in 1..=20 {
+        let body = format!("{{\"type\":\"deposit\",\"client\":1,\"tx\":{tx},\"amount\":\"1\"}}");
+        requests += &format!(
+            "POST /transactions HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+    }
+    let body = "{\"type\":\"withdrawal\",\"client\":1,\"tx\":21,\"amount\":\"20\"}";
+    requests += &format!(
+        "POST /transactions HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    stream.write_all(requests.as_bytes()).unwrap();
+    let mut responses = String::new();
+    stream.read_to_string(&mut responses).unwrap();
+
+    shutdown.store(true, Ordering::Relaxed);
+    let engine = server.join().unwrap();
+
+    assert_eq!(responses.matches("HTTP/1.1 200").count(), 21);
+    assert!(responses.ends_with("{\"tx\":21,\"outcome\":\"applied\"}"));
+    assert!(engine.get_accounts()[&1].total().is_zero());
+}
+
+#[test]
+fn test_admin_endpoint_is_closed_without_a_token() {
+    let api = HttpApi::bind("127.0.0.1:0", None).unwrap();
+    let addr = api.local_addr().unwrap();
+    let shutdown = api.shutdown_flag();
+    let server = thread::spawn(move || {
+        let mut engine = Engine::new();
+        api.run(&mut engine).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET /admin/accounts.csv HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    shutdown.store(true, Ordering::Relaxed);
+    server.join().unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 401"));
+}
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"Bearer secret", b"Bearer secret"));
+    assert!(!constant_time_eq(b"Bearer secreT", b"Bearer secret"));
+    assert!(!constant_time_eq(b"Bearer secret!", b"Bearer secret"));
+    assert!(!constant_time_eq(b"", b"Bearer secret"));
+}
@@ -0,0 +1,356 @@
+use crate::checkpoint::InputPosition;
+use crate::compression::Compression;
+use crate::models::TransactionInput;
+use serde::de::value::{self, MapDeserializer};
+use serde::de::{DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Csv,
+    Jsonl,
+}
+
+impl InputFormat {
+    // Anything that does not look like JSON Lines is read as CSV, as it always was. A
+    // compression extension is looked through, so `events.jsonl.gz` is JSON Lines too.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = match Compression::from_path(path) {
+            Compression::None => path.extension(),
+            _ => path.file_stem().map(Path::new).and_then(Path::extension),
+        };
+
+        match extension.and_then(|extension| extension.to_str()) {
+            Some("jsonl" | "ndjson") => InputFormat::Jsonl,
+            _ => InputFormat::Csv,
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "csv" => Ok(InputFormat::Csv),
+            "jsonl" => Ok(InputFormat::Jsonl),
+            _ => Err(format!("unknown input format `{format}`")),
+        }
+    }
+}
+
+// The fields of `TransactionInput`, in the order header-less files are read by default.
+pub const COLUMNS: [&str; 5] = ["type", "client", "tx", "amount", "timestamp"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvDialect {
+    pub delimiter: u8,
+    // `None` reads quote characters as ordinary data.
+    pub quote: Option<u8>,
+    pub has_headers: bool,
+    // Names of the file's columns in order, used instead of its header row.
+    pub columns: Option<Vec<String>>,
+    // Column names to read as one of `COLUMNS`, e.g. `kind` as `type`.
+    pub renames: HashMap<String, String>,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: Some(b'"'),
+            has_headers: true,
+            columns: None,
+            renames: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Io(io::Error),
+    Parse { line: u64, message: String },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io(e) => write!(f, "{e}"),
+            InputError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+enum Source<R> {
+    Csv {
+        reader: csv::Reader<R>,
+        headers: csv::StringRecord,
+        record: csv::StringRecord,
+    },
+    Jsonl {
+        reader: BufReader<R>,
+        position: InputPosition,
+        buffer: Vec<u8>,
+    },
+}
+
+// Reads transactions one row at a time. A row that does not parse is returned as an
+// error and the next call carries on with the following row. Rows can be read into another
+// type than `TransactionInput`, e.g. one that keeps an unknown transaction type as text.
+pub struct TransactionReader<R, T = TransactionInput> {
+    source: Source<R>,
+    line: u64,
+    rows: PhantomData<T>,
+}
+
+impl<R: Read, T> TransactionReader<R, T> {
+    pub fn new(reader: R, format: InputFormat) -> io::Result<Self> {
+        Self::with_dialect(reader, format, &CsvDialect::default())
+    }
+
+    // The dialect only applies to CSV; JSON Lines records always use the field names.
+    pub fn with_dialect(reader: R, format: InputFormat, dialect: &CsvDialect) -> io::Result<Self> {
+        let source = match format {
+            InputFormat::Csv => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .delimiter(dialect.delimiter)
+                    .quote(dialect.quote.unwrap_or(b'"'))
+                    .quoting(dialect.quote.is_some())
+                    .has_headers(dialect.has_headers)
+                    .from_reader(reader);
+                let headers: csv::StringRecord = match &dialect.columns {
+                    Some(columns) => columns.iter().collect(),
+                    None if dialect.has_headers => reader.headers()?.clone(),
+                    None => COLUMNS.iter().collect(),
+                };
+                let headers = headers
+                    .iter()
+                    .map(|name| dialect.renames.get(name).map_or(name, String::as_str))
+                    .collect();
+                Source::Csv {
+                    reader,
+                    headers,
+                    record: csv::StringRecord::new(),
+                }
+            }
+            InputFormat::Jsonl => Source::Jsonl {
+                reader: BufReader::new(reader),
+                position: InputPosition {
+                    byte: 0,
+                    line: 1,
+                    record: 0,
+                },
+                buffer: Vec::new(),
+            },
+        };
+
+        Ok(Self {
+            source,
+            line: 0,
+            rows: PhantomData,
+        })
+    }
+
+    // Where the next row starts; saving it in a checkpoint lets a later run seek back here.
+    pub fn input_position(&self) -> InputPosition {
+        match &self.source {
+            Source::Csv { reader, .. } => {
+                let position = reader.position();
+                InputPosition {
+                    byte: position.byte(),
+                    line: position.line(),
+                    record: position.record(),
+                }
+            }
+            Source::Jsonl { position, .. } => *position,
+        }
+    }
+
+    // The line the last returned row started on.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+impl<R: Read + Seek, T> TransactionReader<R, T> {
+    pub fn seek(&mut self, to: InputPosition) -> io::Result<()> {
+        match &mut self.source {
+            Source::Csv { reader, .. } => {
+                let mut position = csv::Position::new();
+                position
+                    .set_byte(to.byte)
+                    .set_line(to.line)
+                    .set_record(to.record);
+                reader.seek(position)?;
+            }
+            Source::Jsonl {
+                reader, position, ..
+            } => {
+                reader.seek(SeekFrom::Start(to.byte))?;
+                *position = to;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read, T: DeserializeOwned> Iterator for TransactionReader<R, T> {
+    type Item = Result<T, InputError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.source {
+            Source::Csv {
+                reader,
+                headers,
+                record,
+            } => next_csv(reader, headers, record, &mut self.line),
+            Source::Jsonl {
+                reader,
+                position,
+                buffer,
+            } => next_jsonl(reader, position, buffer, &mut self.line),
+        }
+    }
+}
+
+fn next_csv<R: Read, T: DeserializeOwned>(
+    reader: &mut csv::Reader<R>,
+    headers: &csv::StringRecord,
+    record: &mut csv::StringRecord,
+    line: &mut u64,
+) -> Option<Result<T, InputError>> {
+    match reader.read_record(record) {
+        Ok(false) => None,
+        Ok(true) => {
+            *line = record
+                .position()
+                .map_or(reader.position().line(), |p| p.line());
+            Some(
+                deserialize_record(headers, record).map_err(|e| InputError::Parse {
+                    line: *line,
+                    message: e.to_string(),
+                }),
+            )
+        }
+        Err(e) if e.is_io_error() => Some(Err(InputError::Io(e.into()))),
+        Err(e) => {
+            *line = e.position().map_or(reader.position().line(), |p| p.line());
+            Some(Err(InputError::Parse {
+                line: *line,
+                message: e.to_string(),
+            }))
+        }
+    }
+}
+
+// The csv crate reads any field that looks like a number as a float first, which would cut
+// an amount down to the digits an f64 holds. Here a field is only ever an integer or text,
+// so decimals are parsed from what the file says.
+fn deserialize_record<T: DeserializeOwned>(
+    headers: &csv::StringRecord,
+    record: &csv::StringRecord,
+) -> Result<T, value::Error> {
+    T::deserialize(MapDeserializer::new(
+        headers.iter().zip(record.iter().map(Field)),
+    ))
+}
+
+struct Field<'a>(&'a str);
+
+impl<'de> IntoDeserializer<'de, value::Error> for Field<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Field<'de> {
+    type Error = value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if let Ok(n) = self.0.parse::<u64>() {
+            visitor.visit_u64(n)
+        } else if let Ok(n) = self.0.parse::<i64>() {
+            visitor.visit_i64(n)
+        } else {
+            visitor.visit_borrowed_str(self.0)
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes byte_buf unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+// Blank lines are skipped, but still counted so reported line numbers match the file.
+fn next_jsonl<R: Read, T: DeserializeOwned>(
+    reader: &mut BufReader<R>,
+    position: &mut InputPosition,
+    buffer: &mut Vec<u8>,
+    line: &mut u64,
+) -> Option<Result<T, InputError>> {
+    loop {
+        buffer.clear();
+        let read = match reader.read_until(b'\n', buffer) {
+            Ok(0) => return None,
+            Ok(read) => read,
+            Err(e) => return Some(Err(InputError::Io(e))),
+        };
+
+        *line = position.line;
+        position.byte += read as u64;
+        position.line += 1;
+
+        if buffer.trim_ascii().is_empty() {
+            continue;
+        }
+
+        position.record += 1;
+        return Some(
+            serde_json::from_slice(buffer).map_err(|e| InputError::Parse {
+                line: *line,
+                message: e.to_string(),
+            }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,219 @@
+use super::*;
+use crate::models::TransactionType;
+use rust_decimal_macros::dec;
+use std::io::Cursor;
+
+fn read_all(data: &str, format: InputFormat) -> Vec<Result<TransactionInput, InputError>> {
+    TransactionReader::new(data.as_bytes(), format)
+        .unwrap()
+        .collect()
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(
+        InputFormat::from_path(Path::new("events.jsonl")),
+        InputFormat::Jsonl
+    );
+    assert_eq!(
+        InputFormat::from_path(Path::new("events.ndjson")),
+        InputFormat::Jsonl
+    );
+    assert_eq!(
+        InputFormat::from_path(Path::new("transactions.csv")),
+        InputFormat::Csv
+    );
+    assert_eq!(
+        InputFormat::from_path(Path::new("transactions")),
+        InputFormat::Csv
+    );
+}
+
+#[test]
+fn test_format_from_compressed_path() {
+    assert_eq!(
+        InputFormat::from_path(Path::new("events.jsonl.gz")),
+        InputFormat::Jsonl
+    );
+    assert_eq!(
+        InputFormat::from_path(Path::new("transactions.csv.zst")),
+        InputFormat::Csv
+    );
+}
+
+#[test]
+fn test_amounts_keep_every_digit() {
+    let csv = read_all(
+        "type,client,tx,amount\ndeposit,1,1,123456789012345.6789\n",
+        InputFormat::Csv,
+    );
+    let jsonl = read_all(
+        "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":123456789012345.6789}\n\
+         {\"type\":\"deposit\",\"client\":1,\"tx\":2,\"amount\":\"123456789012345.6789\"}\n",
+        InputFormat::Jsonl,
+    );
+
+    for transaction in csv.into_iter().chain(jsonl) {
+        assert_eq!(
+            transaction.unwrap().amount,
+            Some(dec!(123456789012345.6789))
+        );
+    }
+}
+
+#[test]
+fn test_reads_jsonl_records() {
+    let data = "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":\"1.5\"}\n\
+                {\"type\":\"withdrawal\",\"client\":1,\"tx\":2,\"amount\":0.25,\"timestamp\":7}\n\
+                {\"type\":\"dispute\",\"client\":1,\"tx\":1}\n";
+
+    let transactions: Vec<_> = read_all(data, InputFormat::Jsonl)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(transactions.len(), 3);
+    assert_eq!(transactions[0].amount, Some(dec!(1.5)));
+    assert_eq!(transactions[1].amount, Some(dec!(0.25)));
+    assert_eq!(transactions[1].timestamp, Some(7));
+    assert_eq!(transactions[2].r#type, TransactionType::Dispute);
+    assert_eq!(transactions[2].amount, None);
+}
+
+#[test]
+fn test_jsonl_bad_line_does_not_stop_reading() {
+    let data = "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":1.0}\n\
+                \n\
+                {\"type\":\"bogus\",\"client\":1,\"tx\":2}\n\
+                not json\n\
+                {\"type\":\"deposit\",\"client\":1,\"tx\":3,\"amount\":2.0}";
+
+    let results = read_all(data, InputFormat::Jsonl);
+
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(InputError::Parse { line: 3, .. })));
+    assert!(matches!(results[2], Err(InputError::Parse { line: 4, .. })));
+    assert_eq!(results[3].as_ref().unwrap().tx, 3);
+}
+
+#[test]
+fn test_csv_reports_line_of_bad_row() {
+    let data = "type,client,tx,amount\ndeposit,1,1,1.0\nbogus,1,2,1.0\n";
+
+    let results = read_all(data, InputFormat::Csv);
+
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(InputError::Parse { line: 3, .. })));
+}
+
+#[test]
+fn test_seek_resumes_jsonl_at_position() {
+    let data = "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":1.0}\n\
+                {\"type\":\"deposit\",\"client\":1,\"tx\":2,\"amount\":2.0}\n";
+
+    let mut reader: TransactionReader<_> =
+        TransactionReader::new(Cursor::new(data), InputFormat::Jsonl).unwrap();
+    reader.next().unwrap().unwrap();
+    let position = reader.input_position();
+
+    let mut resumed: TransactionReader<_> =
+        TransactionReader::new(Cursor::new(data), InputFormat::Jsonl).unwrap();
+    resumed.seek(position).unwrap();
+    let next = resumed.next().unwrap().unwrap();
+
+    assert_eq!(next.tx, 2);
+    assert_eq!(resumed.line(), 2);
+    assert!(resumed.next().is_none());
+}
+
+fn read_with_dialect(data: &str, dialect: &CsvDialect) -> Vec<TransactionInput> {
+    TransactionReader::with_dialect(data.as_bytes(), InputFormat::Csv, dialect)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn test_reads_semicolon_delimited_csv() {
+    let dialect = CsvDialect {
+        delimiter: b';',
+        ..CsvDialect::default()
+    };
+
+    let transactions = read_with_dialect("type;client;tx;amount\ndeposit;1;1;1.5\n", &dialect);
+
+    assert_eq!(transactions[0].amount, Some(dec!(1.5)));
+}
+
+#[test]
+fn test_reads_csv_without_headers() {
+    let dialect = CsvDialect {
+        has_headers: false,
+        ..CsvDialect::default()
+    };
+
+    let transactions = read_with_dialect("deposit,1,1,1.5\nwithdrawal,1,2,0.5\n", &dialect);
+
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[1].r#type, TransactionType::Withdrawal);
+}
+
+#[test]
+fn test_reads_columns_in_another_order() {
+    let dialect = CsvDialect {
+        has_headers: false,
+        columns: Some(vec![
+            String::from("tx"),
+            String::from("amount"),
+            String::from("client"),
+            String::from("type"),
+        ]),
+        ..CsvDialect::default()
+    };
+
+    let transactions = read_with_dialect("7,1.5,3,deposit\n", &dialect);
+
+    assert_eq!(transactions[0].tx, 7);
+    assert_eq!(transactions[0].client, 3);
+    assert_eq!(transactions[0].amount, Some(dec!(1.5)));
+}
+
+#[test]
+fn test_renames_header_columns() {
+    let dialect = CsvDialect {
+        renames: HashMap::from([
+            (String::from("kind"), String::from("type")),
+            (String::from("account"), String::from("client")),
+            (String::from("id"), String::from("tx")),
+        ]),
+        ..CsvDialect::default()
+    };
+
+    let transactions = read_with_dialect(
+        "id,account,kind,amount,note\n5,2,deposit,1.0,ignored\n",
+        &dialect,
+    );
+
+    assert_eq!(transactions[0].tx, 5);
+    assert_eq!(transactions[0].client, 2);
+    assert_eq!(transactions[0].r#type, TransactionType::Deposit);
+}
+
+#[test]
+fn test_quoting_can_be_disabled() {
+    let dialect = CsvDialect {
+        quote: None,
+        ..CsvDialect::default()
+    };
+
+    let results: Vec<Result<TransactionInput, _>> = TransactionReader::with_dialect(
+        "type,client,tx,amount\n\"deposit\",1,1,1.0\n".as_bytes(),
+        InputFormat::Csv,
+        &dialect,
+    )
+    .unwrap()
+    .collect();
+
+    assert!(results[0].is_err());
+}
@@ -0,0 +1,141 @@
+use crate::models::TransactionType;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fmt;
+
+// Client accounts hold positive balances for the funds owed to the client; the house
+// accounts carry the opposite side, so every entry and the whole ledger sum to zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LedgerAccount {
+    ClientAvailable(u16),
+    ClientHeld(u16),
+    Settlement,
+    ChargebackLosses,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posting {
+    pub account: LedgerAccount,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub tx: u32,
+    pub r#type: TransactionType,
+    pub postings: Vec<Posting>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LedgerError {
+    Unbalanced { total: Decimal },
+    AccountMismatch { client: u16 },
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::Unbalanced { total } => {
+                write!(f, "ledger does not sum to zero (total {total})")
+            }
+            LedgerError::AccountMismatch { client } => {
+                write!(
+                    f,
+                    "ledger balances of client {client} differ from its account"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+// The balances are all the invariant check needs; the journal grows with every applied
+// transaction, so its entries are only kept once asked for.
+#[derive(Default)]
+pub struct Ledger {
+    entries: Option<Vec<JournalEntry>>,
+    balances: HashMap<LedgerAccount, Decimal>,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn from_balances(balances: HashMap<LedgerAccount, Decimal>) -> Self {
+        Self {
+            entries: None,
+            balances,
+        }
+    }
+
+    pub(crate) fn keep_entries(&mut self) {
+        self.entries.get_or_insert_with(Vec::new);
+    }
+
+    pub(crate) fn record(&mut self, tx: u32, r#type: TransactionType, postings: Vec<Posting>) {
+        debug_assert!(
+            postings
+                .iter()
+                .map(|posting| posting.amount)
+                .sum::<Decimal>()
+                == Decimal::ZERO,
+            "unbalanced journal entry for tx {tx}"
+        );
+
+        for posting in &postings {
+            *self.balances.entry(posting.account).or_default() += posting.amount;
+        }
+
+        if let Some(entries) = &mut self.entries {
+            entries.push(JournalEntry {
+                tx,
+                r#type,
+                postings,
+            });
+        }
+    }
+
+    // Empty unless the entries are kept.
+    pub fn entries(&self) -> &[JournalEntry] {
+        self.entries.as_deref().unwrap_or_default()
+    }
+
+    pub fn entries_for_tx(&self, tx: u32) -> impl Iterator<Item = &JournalEntry> {
+        self.entries().iter().filter(move |entry| entry.tx == tx)
+    }
+
+    pub fn balance(&self, account: LedgerAccount) -> Decimal {
+        self.balances.get(&account).copied().unwrap_or_default()
+    }
+
+    pub fn balances(&self) -> &HashMap<LedgerAccount, Decimal> {
+        &self.balances
+    }
+
+    pub fn check_balanced(&self) -> Result<(), LedgerError> {
+        let total: Decimal = self.balances.values().sum();
+        if total != Decimal::ZERO {
+            return Err(LedgerError::Unbalanced { total });
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn transfer(from: LedgerAccount, to: LedgerAccount, amount: Decimal) -> Vec<Posting> {
+    vec![
+        Posting {
+            account: from,
+            amount: -amount,
+        },
+        Posting {
+            account: to,
+            amount,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,106 @@
+use super::*;
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_record_updates_balances() {
+    let mut ledger = Ledger::new();
+    ledger.keep_entries();
+
+    ledger.record(
+        1,
+        TransactionType::Deposit,
+        transfer(
+            LedgerAccount::Settlement,
+            LedgerAccount::ClientAvailable(1),
+            dec!(10.0),
+        ),
+    );
+
+    assert_eq!(ledger.entries().len(), 1);
+    assert_eq!(
+        ledger.balance(LedgerAccount::ClientAvailable(1)),
+        dec!(10.0)
+    );
+    assert_eq!(ledger.balance(LedgerAccount::Settlement), dec!(-10.0));
+    assert!(ledger.check_balanced().is_ok());
+}
+
+#[test]
+fn test_entries_are_not_kept_by_default() {
+    let mut ledger = Ledger::new();
+
+    ledger.record(
+        1,
+        TransactionType::Deposit,
+        transfer(
+            LedgerAccount::Settlement,
+            LedgerAccount::ClientAvailable(1),
+            dec!(10.0),
+        ),
+    );
+
+    assert!(ledger.entries().is_empty());
+    assert_eq!(
+        ledger.balance(LedgerAccount::ClientAvailable(1)),
+        dec!(10.0)
+    );
+}
+
+#[test]
+fn test_balance_of_unknown_account_is_zero() {
+    let ledger = Ledger::new();
+
+    assert_eq!(ledger.balance(LedgerAccount::ClientHeld(7)), Decimal::ZERO);
+}
+
+#[test]
+fn test_entries_for_tx() {
+    let mut ledger = Ledger::new();
+    ledger.keep_entries();
+    ledger.record(
+        1,
+        TransactionType::Deposit,
+        transfer(
+            LedgerAccount::Settlement,
+            LedgerAccount::ClientAvailable(1),
+            dec!(10.0),
+        ),
+    );
+    ledger.record(
+        2,
+        TransactionType::Deposit,
+        transfer(
+            LedgerAccount::Settlement,
+            LedgerAccount::ClientAvailable(1),
+            dec!(5.0),
+        ),
+    );
+    ledger.record(
+        1,
+        TransactionType::Dispute,
+        transfer(
+            LedgerAccount::ClientAvailable(1),
+            LedgerAccount::ClientHeld(1),
+            dec!(10.0),
+        ),
+    );
+
+    let types: Vec<TransactionType> = ledger.entries_for_tx(1).map(|entry| entry.r#type).collect();
+
+    assert_eq!(
+        types,
+        vec![TransactionType::Deposit, TransactionType::Dispute]
+    );
+}
+
+#[test]
+fn test_check_balanced_detects_imbalance() {
+    let mut balances = HashMap::new();
+    balances.insert(LedgerAccount::ClientAvailable(1), dec!(10.0));
+    let ledger = Ledger::from_balances(balances);
+
+    assert_eq!(
+        ledger.check_balanced(),
+        Err(LedgerError::Unbalanced { total: dec!(10.0) })
+    );
+}
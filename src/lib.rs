@@ -1,5 +1,44 @@
+pub mod changes;
+pub mod checkpoint;
+pub mod compression;
 pub mod engine;
+pub mod event_log;
+pub mod follow;
+pub mod history;
+pub mod http;
+pub mod input;
+pub mod ledger;
+pub mod merge;
+pub mod metrics;
 pub mod models;
+pub mod observer;
+pub mod output;
+pub mod server;
+pub mod stats;
+pub mod validate;
 
+mod codec;
+mod snapshot;
+#[cfg(test)]
+mod test_support;
+
+pub use changes::ChangeFeed;
+pub use compression::{CompressedWriter, Compression};
 pub use engine::Engine;
-pub use models::{AccountOutput, TransactionInput, TransactionType};
+pub use event_log::{EventLog, LogSync};
+pub use follow::FollowReader;
+pub use history::{HistoryEntry, PointInTime};
+pub use http::HttpApi;
+pub use input::{CsvDialect, InputError, InputFormat, TransactionReader};
+pub use ledger::{Ledger, LedgerAccount};
+pub use merge::{MergedReader, MergedRow};
+pub use metrics::Metrics;
+pub use models::{
+    AccountOrder, AccountOutput, Balance, BatchRejected, ChangeOutput, HistoryOutput, OpenDispute,
+    Outcome, Rejection, TransactionInput, TransactionType,
+};
+pub use observer::{EngineEvent, EngineObserver, EventKind};
+pub use output::{OutputFormat, OutputWriter};
+pub use server::{Ack, Reply, RowHandler, Server};
+pub use stats::{RunStats, StatsReport};
+pub use validate::{Finding, Issue, Summary, Validator};
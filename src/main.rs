@@ -1,39 +1,987 @@
-use std::env;
+mod cli;
+
+use std::collections::BTreeSet;
 use std::error::Error;
-use std::io;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cli::{
+    ApiArgs, BalanceAtArgs, ChangesArgs, Cli, Command, EXIT_FAILURE, EXIT_INVALID_INPUT, EmitMode,
+    HistoryArgs, InputArgs, LogArgs, LogFormat, MetricsArgs, OutputArgs, ProcessArgs, ReplayArgs,
+    ReportArgs, ServeArgs, StateArgs, StatsArgs, StatsFormat, ValidateArgs,
+};
+#[cfg(unix)]
+use serde::Serialize;
+use signal_hook::consts::SIGUSR1;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use tracing::{debug, debug_span, info, warn};
+use tracing_subscriber::EnvFilter;
+use transaction_engine::checkpoint::{self, Checkpoint, InputPosition};
+use transaction_engine::{
+    AccountOrder, ChangeFeed, CompressedWriter, Compression, Engine, EngineEvent, EventLog,
+    FollowReader, HttpApi, InputError, MergedReader, Metrics, Outcome, OutputWriter, RowHandler,
+    RunStats, Server, StatsReport, TransactionInput, TransactionReader, Validator, event_log,
+};
+
+// Inputs are processed in the order given; this one stands for stdin.
+const STDIN: &str = "-";
+
+// Rows read ahead of the engine from a followed input.
+const FOLLOW_QUEUE_LEN: usize = 1024;
+
+// How long a followed input is idle before the change records written so far are flushed.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Logs go to stderr so they never mix with the CSV written to stdout. Without
+// `--log-level` the filter comes from RUST_LOG, and defaults to warnings only.
+fn init_logging(args: &LogArgs) -> Result<(), Box<dyn Error>> {
+    let filter = match &args.log_level {
+        Some(level) => EnvFilter::try_new(level)?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr);
+
+    match args.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+
+    Ok(())
+}
+
+fn load_engine(path: Option<&Path>) -> Result<Engine, Box<dyn Error>> {
+    let Some(path) = path else {
+        return Ok(Engine::new());
+    };
+
+    let file = File::open(path)?;
+    Ok(Engine::restore(BufReader::new(file))?)
+}
+
+// Write next to the target and rename, so a crash never leaves a truncated file behind.
+fn write_atomically<F>(path: &Path, write: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(BufWriter<&File>) -> io::Result<()>,
+{
+    let tmp_path = path.with_extension("tmp");
+    let file = File::create(&tmp_path)?;
+    write(BufWriter::new(&file))?;
+    file.sync_all()?;
+    fs::rename(tmp_path, path)?;
+
+    Ok(())
+}
+
+fn save_engine(engine: &Engine, path: &Path) -> Result<(), Box<dyn Error>> {
+    write_atomically(path, |writer| engine.snapshot(writer))
+}
+
+fn load_checkpoint(path: &Path) -> Result<Option<(Engine, Checkpoint)>, Box<dyn Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(Some(checkpoint::load(BufReader::new(file))?))
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN
+}
+
+// Only plain files can be seeked into when resuming from a checkpoint.
+fn is_seekable(path: &Path) -> bool {
+    !is_stdin(path) && Compression::from_path(path) == Compression::None
+}
+
+fn open_reader(path: &Path) -> io::Result<Box<dyn Read>> {
+    if is_stdin(path) {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    Compression::from_path(path).reader(File::open(path)?)
+}
+
+fn open_input(path: &Path, input: &InputArgs) -> io::Result<TransactionReader<Box<dyn Read>>> {
+    TransactionReader::with_dialect(open_reader(path)?, input.format(path), &input.dialect())
+}
+
+fn start_metrics(
+    engine: &mut Engine,
+    args: &MetricsArgs,
+) -> Result<Option<Metrics>, Box<dyn Error>> {
+    if !args.enabled() {
+        return Ok(None);
+    }
+
+    let metrics = Metrics::new();
+    metrics.seed(engine);
+    engine.subscribe(metrics.clone());
+
+    if let Some(addr) = &args.metrics_addr {
+        let local_addr = metrics.serve(addr.as_str())?;
+        info!("serving metrics on http://{local_addr}/metrics");
+    }
+
+    Ok(Some(metrics))
+}
+
+fn start_stats(engine: &mut Engine, enabled: bool) -> Option<RunStats> {
+    enabled.then(|| {
+        let stats = RunStats::new();
+        engine.subscribe(stats.clone());
+        stats
+    })
+}
+
+// Change records are written to a file, compressed by its extension, or to stdout.
+type Changes = ChangeFeed<CompressedWriter<BufWriter<Box<dyn Write + Send>>>>;
+
+fn start_changes(
+    engine: &mut Engine,
+    args: &ChangesArgs,
+    output: &OutputArgs,
+) -> Result<Option<Changes>, Box<dyn Error>> {
+    let Some(path) = &args.changes else {
+        return Ok(None);
+    };
+
+    let (writer, compression): (Box<dyn Write + Send>, _) = if args.on_stdout() {
+        (Box::new(io::stdout()), output.compress_output)
+    } else {
+        (Box::new(File::create(path)?), Compression::from_path(path))
+    };
+    let changes = ChangeFeed::new(
+        compression.writer(BufWriter::new(writer))?,
+        args.changes_format,
+    );
+    engine.subscribe(changes.clone());
+
+    Ok(Some(changes))
+}
+
+fn finish_changes(changes: Option<&Changes>) -> Result<(), Box<dyn Error>> {
+    if let Some(changes) = changes {
+        changes.finish()?.finish()?;
+    }
+
+    Ok(())
+}
+
+fn write_stats<W: Write>(
+    mut writer: W,
+    report: &StatsReport,
+    format: StatsFormat,
+) -> io::Result<()> {
+    match format {
+        StatsFormat::Text => write!(writer, "{report}")?,
+        StatsFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, report)?;
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()
+}
+
+// The summary goes to stderr, or a file, as stdout already carries the accounts.
+fn report_stats(
+    engine: &Engine,
+    stats: Option<&RunStats>,
+    args: &ReportArgs,
+) -> Result<(), Box<dyn Error>> {
+    let Some(stats) = stats else {
+        return Ok(());
+    };
+
+    let report = stats.report(engine);
+    if let Some(path) = &args.stats_file {
+        write_atomically(path, |writer| {
+            write_stats(writer, &report, args.stats_format)
+        })?;
+    }
+    if args.stats {
+        write_stats(io::stderr().lock(), &report, args.stats_format)?;
+    }
+
+    Ok(())
+}
+
+// Checks and persists the result of a run before anything is written to stdout.
+fn finish_run(
+    engine: &Engine,
+    state: &StateArgs,
+    metrics: Option<&Metrics>,
+    metrics_file: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    engine.check_ledger()?;
+
+    if let (Some(metrics), Some(path)) = (metrics, metrics_file) {
+        write_atomically(path, |mut writer| {
+            writer.write_all(metrics.render().as_bytes())?;
+            writer.flush()
+        })?;
+    }
+
+    if let Some(path) = &state.save_state {
+        save_engine(engine, path)?;
+    }
+
+    Ok(())
+}
+
+fn write_rows<T: Serialize, W: Write>(
+    writer: W,
+    output: &OutputArgs,
+    rows: impl IntoIterator<Item = T>,
+) -> io::Result<()> {
+    let mut writer =
+        OutputWriter::new(output.compress_output.writer(writer)?, output.output_format);
+
+    for row in rows {
+        writer.write(&row)?;
+    }
+
+    writer.finish()?.finish()?;
+
+    Ok(())
+}
+
+fn write_output<T: Serialize>(
+    output: &OutputArgs,
+    rows: impl IntoIterator<Item = T>,
+) -> Result<(), Box<dyn Error>> {
+    Ok(write_rows(BufWriter::new(io::stdout()), output, rows)?)
+}
+
+fn write_accounts(
+    engine: &Engine,
+    output: &OutputArgs,
+    order: AccountOrder,
+) -> Result<(), Box<dyn Error>> {
+    let accounts = engine.accounts_by(order);
+    write_output(
+        output,
+        accounts
+            .into_iter()
+            .map(|(client_id, account)| account.to_output(client_id)),
+    )
+}
+
+fn validate(args: &ValidateArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let mut validator = Validator::new();
+    let mut out = BufWriter::new(io::stdout().lock());
+    let dialect = args.input.dialect();
+
+    for path in &args.inputs {
+        let mut write_error = Ok(());
+        validator.validate(
+            open_reader(path)?,
+            args.input.format(path),
+            &dialect,
+            |finding| {
+                if write_error.is_ok() {
+                    write_error = writeln!(
+                        out,
+                        "{}:{}: {}: {}",
+                        path.display(),
+                        finding.line,
+                        finding.issue.as_str(),
+                        finding.issue
+                    );
+                }
+            },
+        )?;
+        write_error?;
+    }
+
+    let summary = validator.summary();
+    writeln!(out, "rows: {}", summary.rows)?;
+    writeln!(out, "rows with issues: {}", summary.rows_with_issues)?;
+    for (issue, count) in &summary.issues {
+        writeln!(out, "{issue}: {count}")?;
+    }
+    out.flush()?;
+
+    Ok(if summary.is_clean() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_INVALID_INPUT)
+    })
+}
+
+fn process(args: &ProcessArgs) -> Result<(), Box<dyn Error>> {
+    let checkpoint_path = args.checkpoint.checkpoint.as_deref();
+    if checkpoint_path.is_some() && !args.inputs.iter().all(|path| is_seekable(path)) {
+        return Err("--checkpoint needs uncompressed input files; stdin and .gz/.zst inputs cannot be resumed".into());
+    }
+    if args.follow.follow && !args.inputs.last().is_some_and(|path| is_seekable(path)) {
+        return Err("--follow needs an uncompressed input file last; stdin and .gz/.zst inputs cannot be followed".into());
+    }
+
+    // A checkpoint left by an interrupted run already contains any state loaded by it.
+    let resumed = match checkpoint_path {
+        Some(path) if args.checkpoint.resume => load_checkpoint(path)?,
+        _ => None,
+    };
+    let (mut engine, resumed) = match resumed {
+        Some((engine, checkpoint)) => {
+            info!(
+                input = checkpoint.input,
+                line = checkpoint.position.line,
+                "resuming from checkpoint"
+            );
+            (engine, Some(checkpoint))
+        }
+        None => (load_engine(args.state.load_state.as_deref())?, None),
+    };
+
+    let metrics = start_metrics(&mut engine, &args.metrics)?;
+    let stats = start_stats(&mut engine, args.report.enabled());
+    let changes = start_changes(&mut engine, &args.changes, &args.output)?;
+
+    let mut event_log = match (&args.event_log, &resumed) {
+        (Some(path), Some(checkpoint)) => match checkpoint.event_log_len {
+            Some(len) => Some(EventLog::open_truncated(path, len)?),
+            None => Some(EventLog::open(path)?),
+        },
+        (Some(path), None) => Some(EventLog::open(path)?),
+        (None, _) => None,
+    };
+    if let Some(log) = &mut event_log {
+        log.set_sync(args.event_log_sync);
+    }
+
+    // Inputs finished before the checkpoint are skipped, and the one it stopped in is
+    // continued from its saved position.
+    let (first_input, resume_at) = match resumed {
+        Some(checkpoint) => (checkpoint.input as usize, Some(checkpoint.position)),
+        None => (0, None),
+    };
+
+    let mut run = Run {
+        engine: &mut engine,
+        metrics: metrics.as_ref(),
+        stats: stats.as_ref(),
+        event_log,
+        checkpoint: checkpoint_path.map(|path| (path, args.checkpoint.checkpoint_every)),
+        rows_since_checkpoint: 0,
+    };
+
+    if args.merge {
+        let readers = args
+            .inputs
+            .iter()
+            .map(|path| open_input(path, &args.input))
+            .collect::<io::Result<Vec<_>>>()?;
+        for row in MergedReader::new(readers) {
+            run.apply(&args.inputs[row.input], row.line, row.result)?;
+        }
+    } else if args.follow.follow {
+        // Earlier inputs are read as usual; only the last one is followed.
+        let last = args.inputs.len() - 1;
+        read_inputs(
+            &mut run,
+            &args.inputs[..last],
+            &args.input,
+            first_input,
+            resume_at.filter(|_| first_input < last),
+        )?;
+        follow_input(
+            &mut run,
+            last,
+            &args.inputs[last],
+            resume_at.filter(|_| first_input == last),
+            changes.as_ref(),
+            args,
+        )?;
+    } else {
+        read_inputs(&mut run, &args.inputs, &args.input, first_input, resume_at)?;
+    }
+
+    if let Some(log) = &run.event_log {
+        log.sync()?;
+    }
+
+    // A run that read its inputs to the end has nothing left to resume. A followed input
+    // never ends, so its final checkpoint is kept for `--resume`.
+    if let Some(path) = checkpoint_path
+        && !args.follow.follow
+        && path.exists()
+    {
+        fs::remove_file(path)?;
+    }
+    finish_changes(changes.as_ref())?;
+
+    finish_run(
+        &engine,
+        &args.state,
+        metrics.as_ref(),
+        args.metrics.metrics_file.as_deref(),
+    )?;
+    report_stats(&engine, stats.as_ref(), &args.report)?;
+    if args.changes.on_stdout() {
+        return Ok(());
+    }
+    write_accounts(&engine, &args.output, args.order.sort_by)
+}
+
+fn replay(args: &ReplayArgs) -> Result<(), Box<dyn Error>> {
+    let mut engine = load_engine(args.state.load_state.as_deref())?;
+    let metrics = start_metrics(&mut engine, &args.metrics)?;
+    let stats = start_stats(&mut engine, args.report.enabled());
+    let changes = start_changes(&mut engine, &args.changes, &args.output)?;
+
+    for path in &args.logs {
+        let reader = BufReader::new(open_reader(path)?);
+        let replayed = event_log::replay_into(&mut engine, reader)?;
+        info!(input = %path.display(), replayed, "event log replayed");
+    }
+    finish_changes(changes.as_ref())?;
+
+    finish_run(
+        &engine,
+        &args.state,
+        metrics.as_ref(),
+        args.metrics.metrics_file.as_deref(),
+    )?;
+    report_stats(&engine, stats.as_ref(), &args.report)?;
+    if args.changes.on_stdout() {
+        return Ok(());
+    }
+    write_accounts(&engine, &args.output, args.order.sort_by)
+}
 
-use transaction_engine::Engine;
+// Applies the rows a `Server` reads, and writes the accounts whenever `dump` is set.
+struct ServeHandler<'a> {
+    engine: &'a mut Engine,
+    metrics: Option<&'a Metrics>,
+    event_log: Option<&'a mut EventLog<File>>,
+    changes: Option<&'a Changes>,
+    dump: Arc<AtomicBool>,
+    args: &'a ServeArgs,
+}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+impl ServeHandler<'_> {
+    fn dump_accounts(&self) -> Result<(), Box<dyn Error>> {
+        let accounts = self.engine.accounts_by(self.args.order.sort_by);
+        let rows = accounts
+            .into_iter()
+            .map(|(client_id, account)| account.to_output(client_id));
 
-    if args.len() < 2 {
-        eprintln!("Use: cargo run -- <csv_file_path>");
-        std::process::exit(1);
+        match &self.args.accounts_file {
+            Some(path) => {
+                write_atomically(path, |writer| write_rows(writer, &self.args.output, rows))
+            }
+            None => write_output(&self.args.output, rows),
+        }
     }
+}
 
-    let mut engine = Engine::new();
-    let filename = &args[1];
-    let mut reader = csv::ReaderBuilder::new()
-        .trim(csv::Trim::All)
-        .from_path(filename)?;
+impl RowHandler for ServeHandler<'_> {
+    fn apply(&mut self, transaction: TransactionInput) -> io::Result<Outcome> {
+        if let Some(log) = self.event_log.as_mut() {
+            log.append(&transaction)?;
+        }
+        let outcome = match self.metrics {
+            Some(metrics) => metrics.time(|| self.engine.process(transaction)),
+            None => self.engine.process(transaction),
+        };
+        // A row is only acknowledged once its change is out.
+        if let Some(changes) = self.changes {
+            changes.flush()?;
+        }
+        Ok(outcome)
+    }
 
-    for result in reader.deserialize() {
-        match result {
-            Ok(record) => engine.process(record),
-            Err(e) => {
-                eprintln!("Parser problem into CSV line: {}", e);
+    // A failed dump is worth a warning, not stopping the rows still coming in.
+    fn tick(&mut self) {
+        if self.dump.swap(false, Ordering::Relaxed) {
+            if self.args.accounts_file.is_none() && self.args.changes.on_stdout() {
+                warn!("the accounts need --accounts-file while changes are written to stdout");
+                return;
+            }
+            match self.dump_accounts() {
+                Ok(()) => info!("accounts written"),
+                Err(e) => warn!(error = %e, "failed to write the accounts"),
             }
         }
     }
+}
+
+// Runs until SIGTERM or SIGINT, then saves the state like the end of any other run.
+fn serve(args: &ServeArgs) -> Result<(), Box<dyn Error>> {
+    let mut engine = load_engine(args.state.load_state.as_deref())?;
+    let metrics = start_metrics(&mut engine, &args.metrics)?;
+    let changes = start_changes(&mut engine, &args.changes, &args.output)?;
+    let mut event_log = args.event_log.as_deref().map(EventLog::open).transpose()?;
+    if let Some(log) = &mut event_log {
+        log.set_sync(args.event_log_sync);
+    }
+
+    let mut server = Server::new(args.input.input_format, args.input.dialect());
+    server.set_acks(!args.no_acks);
+    if let Some(addr) = args.tcp_addr() {
+        let local_addr = server.listen_tcp(addr)?;
+        info!(addr = %local_addr, "accepting connections");
+    }
+    #[cfg(unix)]
+    for path in &args.unix {
+        server.listen_unix(path)?;
+        info!(socket = %path.display(), "accepting connections");
+    }
+    #[cfg(unix)]
+    for path in &args.fifo {
+        server.read_fifo(path)?;
+        info!(fifo = %path.display(), "reading named pipe");
+    }
+    // A second signal exits right away, in case the shutdown is stuck.
+    for signal in [SIGTERM, SIGINT] {
+        signal_hook::flag::register_conditional_shutdown(signal, 1, server.shutdown_flag())?;
+        signal_hook::flag::register(signal, server.shutdown_flag())?;
+    }
+    let dump = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    signal_hook::flag::register(SIGUSR1, Arc::clone(&dump))?;
+
+    server.run(ServeHandler {
+        engine: &mut engine,
+        metrics: metrics.as_ref(),
+        event_log: event_log.as_mut(),
+        changes: changes.as_ref(),
+        dump,
+        args,
+    })?;
+    info!("connections closed, shutting down");
+
+    if let Some(log) = &event_log {
+        log.sync()?;
+    }
+    finish_changes(changes.as_ref())?;
+
+    finish_run(
+        &engine,
+        &args.state,
+        metrics.as_ref(),
+        args.metrics.metrics_file.as_deref(),
+    )
+}
+
+fn api(args: &ApiArgs) -> Result<(), Box<dyn Error>> {
+    let mut engine = load_engine(args.state.load_state.as_deref())?;
+    if args.keep_history {
+        engine.keep_history();
+    }
+    let metrics = start_metrics(&mut engine, &args.metrics)?;
+
+    let mut api = HttpApi::bind(args.listen.as_str(), args.admin_token.clone())?;
+    if let Some(metrics) = &metrics {
+        api.set_metrics(metrics.clone());
+    }
+    for signal in [SIGTERM, SIGINT] {
+        signal_hook::flag::register(signal, api.shutdown_flag())?;
+    }
+    info!(addr = %api.local_addr()?, "answering HTTP requests");
+
+    api.run(&mut engine)?;
+    info!("shutting down");
+
+    finish_run(
+        &engine,
+        &args.state,
+        metrics.as_ref(),
+        args.metrics.metrics_file.as_deref(),
+    )
+}
 
-    let mut writer = csv::Writer::from_writer(io::stdout());
+fn stats(args: &StatsArgs) -> Result<(), Box<dyn Error>> {
+    let mut engine = load_engine(args.state.load_state.as_deref())?;
+    let stats = RunStats::new();
+    engine.subscribe(stats.clone());
 
-    for (&client_id, account) in engine.get_accounts() {
-        writer.serialize(account.to_output(client_id))?;
+    let mut run = Run {
+        engine: &mut engine,
+        metrics: None,
+        stats: Some(&stats),
+        event_log: None,
+        checkpoint: None,
+        rows_since_checkpoint: 0,
+    };
+    read_inputs(&mut run, &args.inputs, &args.input, 0, None)?;
+
+    finish_run(&engine, &args.state, None, None)?;
+    write_stats(
+        BufWriter::new(io::stdout().lock()),
+        &stats.report(&engine),
+        args.stats_format,
+    )?;
+
+    Ok(())
+}
+
+// A saved state holds no history, so query modes rebuild it from an event log, the inputs
+// or both.
+fn query_engine(
+    inputs: &[PathBuf],
+    input: &InputArgs,
+    event_log: Option<&Path>,
+    state: &StateArgs,
+) -> Result<Engine, Box<dyn Error>> {
+    let mut engine = load_engine(state.load_state.as_deref())?;
+    engine.keep_history();
+
+    if let Some(path) = event_log {
+        let reader = BufReader::new(open_reader(path)?);
+        let replayed = event_log::replay_into(&mut engine, reader)?;
+        info!(input = %path.display(), replayed, "event log replayed");
     }
 
-    writer.flush()?;
+    let mut run = Run {
+        engine: &mut engine,
+        metrics: None,
+        stats: None,
+        event_log: None,
+        checkpoint: None,
+        rows_since_checkpoint: 0,
+    };
+    read_inputs(&mut run, inputs, input, 0, None)?;
+
+    finish_run(&engine, state, None, None)?;
+
+    Ok(engine)
+}
+
+fn history(args: &HistoryArgs) -> Result<(), Box<dyn Error>> {
+    let engine = query_engine(
+        &args.inputs,
+        &args.input,
+        args.event_log.as_deref(),
+        &args.state,
+    )?;
+
+    write_output(
+        &args.output,
+        engine
+            .history(args.client)
+            .iter()
+            .map(|entry| entry.to_output()),
+    )
+}
+
+fn balance_at(args: &BalanceAtArgs) -> Result<(), Box<dyn Error>> {
+    let engine = query_engine(
+        &args.inputs,
+        &args.input,
+        args.event_log.as_deref(),
+        &args.state,
+    )?;
+
+    let at = args.at.point_in_time();
+    let Some(balance) = engine.balance_at(args.client, at) else {
+        return Err(format!("there is no balance of client {} {at}", args.client).into());
+    };
+
+    write_output(&args.output, [balance.to_output(args.client)])
+}
+
+// Reads the inputs one after another, continuing the first one from `resume_at` if given.
+fn read_inputs(
+    run: &mut Run,
+    inputs: &[PathBuf],
+    input: &InputArgs,
+    first_input: usize,
+    mut resume_at: Option<InputPosition>,
+) -> Result<(), Box<dyn Error>> {
+    let dialect = input.dialect();
+
+    for (index, path) in inputs.iter().enumerate().skip(first_input) {
+        if is_seekable(path) {
+            let mut reader =
+                TransactionReader::with_dialect(File::open(path)?, input.format(path), &dialect)?;
+            if let Some(position) = resume_at.take() {
+                reader.seek(position)?;
+            }
+            run.process(index, path, &mut reader)?;
+        } else {
+            let mut reader =
+                TransactionReader::with_dialect(open_reader(path)?, input.format(path), &dialect)?;
+            run.process(index, path, &mut reader)?;
+        }
+    }
 
     Ok(())
 }
+
+// Rows are read on another thread, which waits for the input to grow, so that the accounts
+// are still written on time while nothing is appended. SIGTERM or Ctrl-C ends the input at
+// its current end, and the run finishes as if it had been read to the end.
+fn follow_input(
+    run: &mut Run,
+    index: usize,
+    path: &Path,
+    resume_at: Option<InputPosition>,
+    changes: Option<&Changes>,
+    args: &ProcessArgs,
+) -> Result<(), Box<dyn Error>> {
+    let follower = FollowReader::new(File::open(path)?);
+    for signal in [SIGTERM, SIGINT] {
+        signal_hook::flag::register(signal, follower.stop_flag())?;
+    }
+
+    let changed = Arc::new(Mutex::new(BTreeSet::new()));
+    if args.follow.emit == EmitMode::Changes {
+        let changed = Arc::clone(&changed);
+        run.engine.subscribe(move |event: &EngineEvent| {
+            if event.before != event.after {
+                lock(&changed).insert(event.client);
+            }
+        });
+    }
+
+    let format = args.input.format(path);
+    let dialect = args.input.dialect();
+    let (rows, received) = mpsc::sync_channel(FOLLOW_QUEUE_LEN);
+    let reader = thread::spawn(move || -> io::Result<()> {
+        let mut reader = TransactionReader::with_dialect(follower, format, &dialect)?;
+        if let Some(position) = resume_at {
+            reader.seek(position)?;
+        }
+        // Where reading starts, past any header, in case no row follows before a stop.
+        if rows.send((0, reader.input_position(), None)).is_err() {
+            return Ok(());
+        }
+        while let Some(row) = reader.next() {
+            if rows
+                .send((reader.line(), reader.input_position(), Some(row)))
+                .is_err()
+            {
+                break;
+            }
+        }
+        Ok(())
+    });
+    info!(input = %path.display(), "following input");
+
+    let every = Duration::from_secs(args.follow.emit_every);
+    let mut next_emit = Instant::now() + every;
+    let mut last_position = None;
+    loop {
+        let wait = next_emit.saturating_duration_since(Instant::now());
+        match received.recv_timeout(wait.min(FLUSH_INTERVAL)) {
+            Ok((line, position, row)) => {
+                if let Some(row) = row {
+                    run.apply(path, line, row)?;
+                    run.row_done(index, path, position)?;
+                }
+                last_position = Some(position);
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(changes) = changes {
+                    changes.flush()?;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if Instant::now() >= next_emit {
+            // Change records on stdout take the place of the accounts.
+            if !args.changes.on_stdout() {
+                emit_accounts(run.engine, &changed, args)?;
+            }
+            next_emit = Instant::now() + every;
+        }
+    }
+    info!(input = %path.display(), "stopped following input");
+
+    reader
+        .join()
+        .map_err(|_| io::Error::other("follow reader panicked"))??;
+
+    // A stopped follow is resumed where it left off, not from the rows of the last
+    // periodic checkpoint.
+    if let Some(position) = last_position {
+        run.save_checkpoint(index, path, position)?;
+    }
+
+    Ok(())
+}
+
+fn emit_accounts(
+    engine: &Engine,
+    changed: &Mutex<BTreeSet<u16>>,
+    args: &ProcessArgs,
+) -> Result<(), Box<dyn Error>> {
+    match args.follow.emit {
+        EmitMode::Snapshot => write_accounts(engine, &args.output, args.order.sort_by),
+        EmitMode::Changes => {
+            let changed = mem::take(&mut *lock(changed));
+            if changed.is_empty() {
+                return Ok(());
+            }
+            let accounts = engine.accounts_by(args.order.sort_by);
+            write_output(
+                &args.output,
+                accounts
+                    .into_iter()
+                    .filter(|(client_id, _)| changed.contains(client_id))
+                    .map(|(client_id, account)| account.to_output(client_id)),
+            )
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// What every row needs on its way into the engine, shared by all inputs of a run.
+struct Run<'a> {
+    engine: &'a mut Engine,
+    metrics: Option<&'a Metrics>,
+    stats: Option<&'a RunStats>,
+    event_log: Option<EventLog<File>>,
+    // Where to save checkpoints, and how many rows apart.
+    checkpoint: Option<(&'a Path, u64)>,
+    rows_since_checkpoint: u64,
+}
+
+impl Run<'_> {
+    fn process<R: Read>(
+        &mut self,
+        input: usize,
+        path: &Path,
+        reader: &mut TransactionReader<R>,
+    ) -> Result<(), Box<dyn Error>> {
+        while let Some(row) = reader.next() {
+            self.apply(path, reader.line(), row)?;
+            self.row_done(input, path, reader.input_position())?;
+        }
+
+        Ok(())
+    }
+
+    // `position` is where the row after the one just applied starts.
+    fn row_done(
+        &mut self,
+        input: usize,
+        path: &Path,
+        position: InputPosition,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rows_since_checkpoint += 1;
+        if let Some((_, every)) = self.checkpoint
+            && self.rows_since_checkpoint >= every
+        {
+            self.save_checkpoint(input, path, position)?;
+        }
+
+        Ok(())
+    }
+
+    fn save_checkpoint(
+        &mut self,
+        input: usize,
+        path: &Path,
+        position: InputPosition,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some((checkpoint_path, _)) = self.checkpoint {
+            let checkpoint = Checkpoint {
+                input: input as u64,
+                position,
+                event_log_len: None,
+            };
+            save_checkpoint(
+                self.engine,
+                checkpoint_path,
+                checkpoint,
+                self.event_log.as_ref(),
+            )?;
+            debug!(input = %path.display(), line = checkpoint.position.line, "checkpoint saved");
+            self.rows_since_checkpoint = 0;
+        }
+
+        Ok(())
+    }
+
+    fn apply(
+        &mut self,
+        path: &Path,
+        line: u64,
+        row: Result<TransactionInput, InputError>,
+    ) -> Result<(), Box<dyn Error>> {
+        match row {
+            Ok(transaction) => {
+                let _row = debug_span!("row", input = %path.display(), line).entered();
+                if let Some(log) = self.event_log.as_mut() {
+                    log.append(&transaction)?;
+                }
+                match self.metrics {
+                    Some(metrics) => metrics.time(|| self.engine.process(transaction)),
+                    None => self.engine.process(transaction),
+                };
+            }
+            Err(InputError::Io(e)) => return Err(e.into()),
+            Err(InputError::Parse { line, message }) => {
+                warn!(input = %path.display(), line, error = %message, "skipping unparseable row");
+                if let Some(stats) = self.stats {
+                    stats.parse_failed();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn save_checkpoint(
+    engine: &Engine,
+    path: &Path,
+    mut checkpoint: Checkpoint,
+    event_log: Option<&EventLog<File>>,
+) -> Result<(), Box<dyn Error>> {
+    checkpoint.event_log_len = match event_log {
+        Some(log) => {
+            log.sync()?;
+            Some(log.byte_len()?)
+        }
+        None => None,
+    };
+
+    write_atomically(path, |writer| checkpoint::save(writer, engine, &checkpoint))
+}
+
+fn run(command: &Command) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::Process(args) => process(args)?,
+        Command::Validate(args) => return validate(args),
+        Command::Stats(args) => stats(args)?,
+        Command::Replay(args) => replay(args)?,
+        Command::Serve(args) => serve(args)?,
+        Command::Api(args) => api(args)?,
+        Command::History(args) => history(args)?,
+        Command::BalanceAt(args) => balance_at(args)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse_args();
+
+    match init_logging(&cli.logging).and_then(|()| run(&cli.command)) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
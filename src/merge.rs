@@ -0,0 +1,104 @@
+use crate::input::{InputError, TransactionReader};
+use crate::models::TransactionInput;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::io::Read;
+
+pub struct MergedRow {
+    pub input: usize,
+    pub line: u64,
+    pub result: Result<TransactionInput, InputError>,
+}
+
+struct Head {
+    key: (Option<u64>, u32, usize),
+    line: u64,
+    transaction: TransactionInput,
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+// Interleaves inputs that are each already in timestamp order, ties going to the lower tx
+// id. Only the next row of every input is held in memory. A row without a timestamp sorts
+// first, so it is taken as soon as it reaches the front of its input.
+pub struct MergedReader<R> {
+    readers: Vec<TransactionReader<R>>,
+    heads: BinaryHeap<Reverse<Head>>,
+    // Inputs whose head was taken and must be read again before the next row is chosen.
+    pending: Vec<usize>,
+}
+
+impl<R: Read> MergedReader<R> {
+    pub fn new(readers: Vec<TransactionReader<R>>) -> Self {
+        let pending = (0..readers.len()).rev().collect();
+
+        Self {
+            readers,
+            heads: BinaryHeap::new(),
+            pending,
+        }
+    }
+}
+
+impl<R: Read> Iterator for MergedReader<R> {
+    type Item = MergedRow;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&input) = self.pending.last() {
+            let reader = &mut self.readers[input];
+            match reader.next() {
+                None => {
+                    self.pending.pop();
+                }
+                Some(Ok(transaction)) => {
+                    self.pending.pop();
+                    self.heads.push(Reverse(Head {
+                        key: (transaction.timestamp, transaction.tx, input),
+                        line: reader.line(),
+                        transaction,
+                    }));
+                }
+                // Errors are not ordered; the input stays pending so the next call goes
+                // on reading it.
+                Some(Err(e)) => {
+                    return Some(MergedRow {
+                        input,
+                        line: reader.line(),
+                        result: Err(e),
+                    });
+                }
+            }
+        }
+
+        let Reverse(head) = self.heads.pop()?;
+        let input = head.key.2;
+        self.pending.push(input);
+
+        Some(MergedRow {
+            input,
+            line: head.line,
+            result: Ok(head.transaction),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,69 @@
+use super::*;
+use crate::input::InputFormat;
+
+fn reader(data: &'static str) -> TransactionReader<&'static [u8]> {
+    TransactionReader::new(data.as_bytes(), InputFormat::Csv).unwrap()
+}
+
+fn merged_txs(readers: Vec<TransactionReader<&'static [u8]>>) -> Vec<(usize, u32)> {
+    MergedReader::new(readers)
+        .map(|row| (row.input, row.result.unwrap().tx))
+        .collect()
+}
+
+#[test]
+fn test_merges_by_timestamp() {
+    let europe = reader(
+        "type,client,tx,amount,timestamp\n\
+         deposit,1,1,1.0,10\n\
+         deposit,1,2,1.0,30\n\
+         deposit,1,3,1.0,50\n",
+    );
+    let america = reader(
+        "type,client,tx,amount,timestamp\n\
+         deposit,2,4,1.0,20\n\
+         deposit,2,5,1.0,40\n",
+    );
+
+    let txs = merged_txs(vec![europe, america]);
+
+    assert_eq!(txs, vec![(0, 1), (1, 4), (0, 2), (1, 5), (0, 3)]);
+}
+
+#[test]
+fn test_equal_timestamps_go_by_tx_id() {
+    let first = reader("type,client,tx,amount,timestamp\ndeposit,1,9,1.0,10\n");
+    let second = reader("type,client,tx,amount,timestamp\ndeposit,2,3,1.0,10\n");
+
+    let txs = merged_txs(vec![first, second]);
+
+    assert_eq!(txs, vec![(1, 3), (0, 9)]);
+}
+
+#[test]
+fn test_rows_without_timestamp_are_taken_first() {
+    let timed = reader("type,client,tx,amount,timestamp\ndeposit,1,1,1.0,10\n");
+    let untimed = reader("type,client,tx,amount\ndeposit,2,2,1.0\n");
+
+    let txs = merged_txs(vec![timed, untimed]);
+
+    assert_eq!(txs, vec![(1, 2), (0, 1)]);
+}
+
+#[test]
+fn test_bad_rows_are_reported_with_their_input_and_line() {
+    let good = reader("type,client,tx,amount,timestamp\ndeposit,1,1,1.0,10\n");
+    let bad = reader(
+        "type,client,tx,amount,timestamp\n\
+         bogus,2,2,1.0,5\n\
+         deposit,2,3,1.0,20\n",
+    );
+
+    let rows: Vec<MergedRow> = MergedReader::new(vec![good, bad]).collect();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!((rows[0].input, rows[0].line), (1, 2));
+    assert!(rows[0].result.is_err());
+    assert_eq!(rows[1].result.as_ref().unwrap().tx, 1);
+    assert_eq!((rows[2].input, rows[2].line), (1, 3));
+}
@@ -0,0 +1,238 @@
+use crate::Engine;
+use crate::models::TransactionType;
+use crate::observer::{EngineEvent, EngineObserver, EventKind};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PREFIX: &str = "transaction_engine";
+
+// A scraper that stalls is dropped after this long rather than holding its thread forever.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+// Only the request line is read; anything longer is not a metrics request.
+const MAX_REQUEST_LINE: u64 = 8 * 1024;
+
+const LATENCY_BUCKETS: [f64; 9] = [
+    0.000_001, 0.000_005, 0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01,
+];
+
+#[derive(Default)]
+struct Registry {
+    transactions: BTreeMap<(&'static str, &'static str), u64>,
+    open_disputes: i64,
+    held_funds: Decimal,
+    locked_accounts: u64,
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    latency_count: u64,
+}
+
+// A cheap handle: clones share the same registry, so one can be subscribed to the engine
+// while another renders or serves it.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Gauges are kept up to date from events, so they need a starting point when the
+    // engine was restored from a saved state.
+    pub fn seed(&self, engine: &Engine) {
+        let mut registry = self.registry();
+        registry.open_disputes = engine.open_disputes().len() as i64;
+        registry.held_funds = engine
+            .get_accounts()
+            .values()
+            .map(|account| account.held)
+            .sum();
+        registry.locked_accounts = engine
+            .get_accounts()
+            .values()
+            .filter(|account| account.locked)
+            .count() as u64;
+    }
+
+    pub fn observe_latency(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let mut registry = self.registry();
+
+        for (bucket, &bound) in LATENCY_BUCKETS.iter().enumerate() {
+            if seconds <= bound {
+                registry.latency_buckets[bucket] += 1;
+            }
+        }
+        registry.latency_sum += seconds;
+        registry.latency_count += 1;
+    }
+
+    pub fn time<T>(&self, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.observe_latency(started.elapsed());
+        result
+    }
+
+    pub fn render(&self) -> String {
+        let registry = self.registry();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "transactions_total",
+            "counter",
+            "Processed transactions by type and outcome.",
+        );
+        for ((r#type, outcome), count) in &registry.transactions {
+            let _ = writeln!(
+                out,
+                "{PREFIX}_transactions_total{{type=\"{type}\",outcome=\"{outcome}\"}} {count}"
+            );
+        }
+
+        header(
+            &mut out,
+            "open_disputes",
+            "gauge",
+            "Deposits currently under dispute.",
+        );
+        let _ = writeln!(out, "{PREFIX}_open_disputes {}", registry.open_disputes);
+
+        header(
+            &mut out,
+            "held_funds",
+            "gauge",
+            "Funds held by open disputes across all accounts.",
+        );
+        let _ = writeln!(out, "{PREFIX}_held_funds {}", registry.held_funds);
+
+        header(&mut out, "locked_accounts", "gauge", "Locked accounts.");
+        let _ = writeln!(out, "{PREFIX}_locked_accounts {}", registry.locked_accounts);
+
+        header(
+            &mut out,
+            "processing_seconds",
+            "histogram",
+            "Time spent processing one transaction.",
+        );
+        for (bucket, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{PREFIX}_processing_seconds_bucket{{le=\"{bound}\"}} {}",
+                registry.latency_buckets[bucket]
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{PREFIX}_processing_seconds_bucket{{le=\"+Inf\"}} {}",
+            registry.latency_count
+        );
+        let _ = writeln!(
+            out,
+            "{PREFIX}_processing_seconds_sum {}",
+            registry.latency_sum
+        );
+        let _ = writeln!(
+            out,
+            "{PREFIX}_processing_seconds_count {}",
+            registry.latency_count
+        );
+
+        out
+    }
+
+    // Serves `GET /metrics` from a background thread for as long as the process runs. Each
+    // connection gets its own thread, so a slow client cannot hold up the others.
+    pub fn serve<A: ToSocketAddrs>(&self, addr: A) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let metrics = self.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let metrics = metrics.clone();
+                thread::spawn(move || metrics.respond(stream));
+            }
+        });
+
+        Ok(local_addr)
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+        stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+
+        let mut request_line = String::new();
+        BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+
+        let (status, body) = match request_line.split_whitespace().nth(1) {
+            Some("/metrics") => ("200 OK", self.render()),
+            _ => ("404 Not Found", String::from("not found\n")),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        stream.flush()
+    }
+}
+
+impl EngineObserver for Metrics {
+    fn on_event(&mut self, event: &EngineEvent) {
+        let mut registry = self.registry();
+
+        let (r#type, outcome) = match &event.kind {
+            EventKind::Deposited { .. } => (TransactionType::Deposit, "applied"),
+            EventKind::Withdrawn { .. } => (TransactionType::Withdrawal, "applied"),
+            EventKind::DisputeOpened { amount } => {
+                registry.open_disputes += 1;
+                registry.held_funds += amount;
+                (TransactionType::Dispute, "applied")
+            }
+            EventKind::DisputeResolved { amount } => {
+                registry.open_disputes -= 1;
+                registry.held_funds -= amount;
+                (TransactionType::Resolve, "applied")
+            }
+            EventKind::ChargedBack { amount } => {
+                registry.open_disputes -= 1;
+                registry.held_funds -= amount;
+                (TransactionType::Chargeback, "applied")
+            }
+            EventKind::Rejected { r#type, rejection } => (*r#type, rejection.as_str()),
+            EventKind::AccountLocked => {
+                registry.locked_accounts += 1;
+                return;
+            }
+        };
+
+        *registry
+            .transactions
+            .entry((r#type.as_str(), outcome))
+            .or_default() += 1;
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {PREFIX}_{name} {help}");
+    let _ = writeln!(out, "# TYPE {PREFIX}_{name} {kind}");
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,86 @@
+use super::*;
+use crate::test_support::{new_tx, observed};
+use rust_decimal_macros::dec;
+use std::io::Read;
+
+#[test]
+fn test_counts_transactions_by_type_and_outcome() {
+    let (mut engine, metrics) = observed(Metrics::new());
+
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))));
+    engine.process(new_tx(1, 2, TransactionType::Deposit, Some(dec!(10.0))));
+    engine.process(new_tx(1, 3, TransactionType::Withdrawal, Some(dec!(50.0))));
+
+    let rendered = metrics.render();
+    assert!(rendered.contains(
+        "transaction_engine_transactions_total{type=\"deposit\",outcome=\"applied\"} 2\n"
+    ));
+    assert!(rendered.contains(
+        "transaction_engine_transactions_total{type=\"withdrawal\",outcome=\"insufficient_funds\"} 1\n"
+    ));
+}
+
+#[test]
+fn test_tracks_disputes_held_funds_and_locks() {
+    let (mut engine, metrics) = observed(Metrics::new());
+
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.5))));
+    engine.process(new_tx(2, 2, TransactionType::Deposit, Some(dec!(4.0))));
+    engine.process(new_tx(1, 1, TransactionType::Dispute, None));
+    engine.process(new_tx(2, 2, TransactionType::Dispute, None));
+    engine.process(new_tx(2, 2, TransactionType::Chargeback, None));
+
+    let rendered = metrics.render();
+    assert!(rendered.contains("transaction_engine_open_disputes 1\n"));
+    assert!(rendered.contains("transaction_engine_held_funds 10.5\n"));
+    assert!(rendered.contains("transaction_engine_locked_accounts 1\n"));
+}
+
+#[test]
+fn test_seed_from_restored_engine() {
+    let (mut engine, _) = observed(Metrics::new());
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(3.0))));
+    engine.process(new_tx(1, 1, TransactionType::Dispute, None));
+
+    let metrics = Metrics::new();
+    metrics.seed(&engine);
+
+    let rendered = metrics.render();
+    assert!(rendered.contains("transaction_engine_open_disputes 1\n"));
+    assert!(rendered.contains("transaction_engine_held_funds 3.0\n"));
+    assert!(rendered.contains("transaction_engine_locked_accounts 0\n"));
+}
+
+#[test]
+fn test_latency_histogram() {
+    let metrics = Metrics::new();
+
+    metrics.observe_latency(Duration::from_micros(2));
+    metrics.observe_latency(Duration::from_millis(20));
+
+    let rendered = metrics.render();
+    assert!(rendered.contains("transaction_engine_processing_seconds_bucket{le=\"0.000005\"} 1\n"));
+    assert!(rendered.contains("transaction_engine_processing_seconds_bucket{le=\"+Inf\"} 2\n"));
+    assert!(rendered.contains("transaction_engine_processing_seconds_count 2\n"));
+}
+
+#[test]
+fn test_serve_exposes_metrics_over_http() {
+    let metrics = Metrics::new();
+    let addr = metrics.serve("127.0.0.1:0").unwrap();
+    // A client that never sends its request must not keep the others waiting.
+    let _idle = TcpStream::connect(addr).unwrap();
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("# TYPE transaction_engine_open_disputes gauge\n"));
+}
@@ -1,7 +1,11 @@
+use crate::output::{serialize_amount, serialize_optional_amount};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum TransactionType {
     Deposit,
@@ -11,24 +15,238 @@ pub enum TransactionType {
     Chargeback,
 }
 
-#[derive(Debug, Deserialize)]
+impl TransactionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::Deposit => "deposit",
+            TransactionType::Withdrawal => "withdrawal",
+            TransactionType::Dispute => "dispute",
+            TransactionType::Resolve => "resolve",
+            TransactionType::Chargeback => "chargeback",
+        }
+    }
+}
+
+impl FromStr for TransactionType {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "deposit" => Ok(TransactionType::Deposit),
+            "withdrawal" => Ok(TransactionType::Withdrawal),
+            "dispute" => Ok(TransactionType::Dispute),
+            "resolve" => Ok(TransactionType::Resolve),
+            "chargeback" => Ok(TransactionType::Chargeback),
+            _ => Err(format!("unknown transaction type `{name}`")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rejection {
+    Duplicate,
+    AccountLocked,
+    MissingAmount,
+    InsufficientFunds,
+    UnknownTransaction,
+    NotADeposit,
+    ClientMismatch,
+    AlreadyDisputed,
+    NotDisputed,
+}
+
+impl Rejection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rejection::Duplicate => "duplicate",
+            Rejection::AccountLocked => "account_locked",
+            Rejection::MissingAmount => "missing_amount",
+            Rejection::InsufficientFunds => "insufficient_funds",
+            Rejection::UnknownTransaction => "unknown_transaction",
+            Rejection::NotADeposit => "not_a_deposit",
+            Rejection::ClientMismatch => "client_mismatch",
+            Rejection::AlreadyDisputed => "already_disputed",
+            Rejection::NotDisputed => "not_disputed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Applied,
+    Rejected(Rejection),
+}
+
+impl Outcome {
+    pub fn is_applied(&self) -> bool {
+        matches!(self, Outcome::Applied)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Applied => "applied",
+            Outcome::Rejected(rejection) => rejection.as_str(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchRejected {
+    pub index: usize,
+    pub rejection: Rejection,
+}
+
+impl fmt::Display for BatchRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "batch rejected: row {} was rejected ({})",
+            self.index,
+            self.rejection.as_str()
+        )
+    }
+}
+
+impl std::error::Error for BatchRejected {}
+
+impl From<Result<(), Rejection>> for Outcome {
+    fn from(result: Result<(), Rejection>) -> Self {
+        match result {
+            Ok(()) => Outcome::Applied,
+            Err(rejection) => Outcome::Rejected(rejection),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct TransactionInput {
     #[serde(rename = "type")]
     pub r#type: TransactionType,
     pub client: u16,
     pub tx: u32,
     pub amount: Option<Decimal>,
+    // Optional producer timestamp; the column may be absent from the input.
+    #[serde(default)]
+    pub timestamp: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct AccountOutput {
-    client: u16,
-    available: Decimal,
-    held: Decimal,
-    total: Decimal,
-    locked: bool,
+    pub(crate) client: u16,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) available: Decimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) held: Decimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total: Decimal,
+    pub(crate) locked: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccountOrder {
+    #[default]
+    Client,
+    Available,
+    Held,
+    Total,
+    Locked,
+}
+
+impl AccountOrder {
+    // Accounts that compare equal keep their relative order, so ties stay in client order
+    // when the input already is.
+    pub fn compare(self, a: &Account, b: &Account) -> Ordering {
+        match self {
+            AccountOrder::Client => Ordering::Equal,
+            AccountOrder::Available => a.available.cmp(&b.available),
+            AccountOrder::Held => a.held.cmp(&b.held),
+            AccountOrder::Total => a.total().cmp(&b.total()),
+            AccountOrder::Locked => a.locked.cmp(&b.locked),
+        }
+    }
 }
 
+impl FromStr for AccountOrder {
+    type Err = String;
+
+    fn from_str(column: &str) -> Result<Self, Self::Err> {
+        match column {
+            "client" => Ok(AccountOrder::Client),
+            "available" => Ok(AccountOrder::Available),
+            "held" => Ok(AccountOrder::Held),
+            "total" => Ok(AccountOrder::Total),
+            "locked" => Ok(AccountOrder::Locked),
+            _ => Err(format!("unknown account column `{column}`")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryOutput {
+    pub(crate) seq: u64,
+    pub(crate) tx: u32,
+    pub(crate) r#type: &'static str,
+    #[serde(serialize_with = "serialize_optional_amount")]
+    pub(crate) amount: Option<Decimal>,
+    pub(crate) outcome: &'static str,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) available: Decimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) held: Decimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total: Decimal,
+    pub(crate) locked: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeOutput {
+    pub(crate) seq: u64,
+    pub(crate) client: u16,
+    pub(crate) tx: u32,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) old_available: Decimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) old_held: Decimal,
+    pub(crate) old_locked: bool,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) new_available: Decimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) new_held: Decimal,
+    pub(crate) new_locked: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Balance {
+    pub available: Decimal,
+    pub held: Decimal,
+    pub locked: bool,
+}
+
+impl Balance {
+    pub fn total(&self) -> Decimal {
+        self.available + self.held
+    }
+
+    pub fn to_output(&self, client_id: u16) -> AccountOutput {
+        AccountOutput {
+            client: client_id,
+            available: self.available.round_dp(4),
+            held: self.held.round_dp(4),
+            total: self.total().round_dp(4),
+            locked: self.locked,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpenDispute {
+    pub tx: u32,
+    pub client: u16,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: Decimal,
+}
+
+#[derive(Clone)]
 pub struct Deposit {
     pub(crate) tx: u32,
     pub(crate) client_id: u16,
@@ -49,6 +267,7 @@ impl Deposit {
     }
 }
 
+#[derive(Clone)]
 pub enum Transaction {
     Deposit(Deposit),
     Withdrawal,
@@ -63,12 +282,19 @@ impl Transaction {
     }
 }
 
+#[derive(Clone)]
 pub struct Account {
     pub(crate) available: Decimal,
     pub(crate) held: Decimal,
     pub(crate) locked: bool,
 }
 
+impl Default for Account {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Account {
     pub fn new() -> Self {
         Self {
@@ -82,15 +308,17 @@ impl Account {
         self.available + self.held
     }
 
-    pub fn to_output(&self, client_id: u16) -> AccountOutput {
-        AccountOutput {
-            client: client_id,
-            available: self.available.round_dp(4),
-            held: self.held.round_dp(4),
-            total: self.total().round_dp(4),
+    pub fn balance(&self) -> Balance {
+        Balance {
+            available: self.available,
+            held: self.held,
             locked: self.locked,
         }
     }
+
+    pub fn to_output(&self, client_id: u16) -> AccountOutput {
+        self.balance().to_output(client_id)
+    }
 }
 
 #[cfg(test)]
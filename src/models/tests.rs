@@ -8,6 +8,7 @@ fn test_deposit_try_new_valid() {
         client: 1,
         tx: 10,
         amount: Some(dec!(100.0)),
+        timestamp: None,
     };
 
     let new_deposit = Deposit::try_new(&input);
@@ -17,7 +18,7 @@ fn test_deposit_try_new_valid() {
     assert_eq!(deposit.client_id, 1);
     assert_eq!(deposit.tx, 10);
     assert_eq!(deposit.amount, dec!(100.0));
-    assert_eq!(deposit.under_dispute, false);
+    assert!(!deposit.under_dispute);
 }
 
 #[test]
@@ -27,6 +28,7 @@ fn test_deposit_try_new_missing_amount() {
         client: 1,
         tx: 10,
         amount: None,
+        timestamp: None,
     };
 
     assert!(Deposit::try_new(&input).is_none());
@@ -77,3 +79,25 @@ fn test_account_output_rounding() {
     assert_eq!(output.held, dec!(20.1234));
     assert_eq!(output.total, dec!(30.2469));
 }
+
+#[test]
+fn test_transaction_input_timestamp_column_is_optional() {
+    let data = "type,client,tx,amount\ndeposit,1,1,1.5\n";
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+
+    let input: TransactionInput = reader.deserialize().next().unwrap().unwrap();
+
+    assert_eq!(input.amount, Some(dec!(1.5)));
+    assert_eq!(input.timestamp, None);
+}
+
+#[test]
+fn test_transaction_input_reads_timestamp() {
+    let data = "type,client,tx,amount,timestamp\ndispute,1,1,,1700000000\n";
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+
+    let input: TransactionInput = reader.deserialize().next().unwrap().unwrap();
+
+    assert_eq!(input.amount, None);
+    assert_eq!(input.timestamp, Some(1_700_000_000));
+}
@@ -0,0 +1,71 @@
+use crate::models::{Balance, ChangeOutput, Rejection, TransactionType};
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    Deposited {
+        amount: Decimal,
+    },
+    Withdrawn {
+        amount: Decimal,
+    },
+    DisputeOpened {
+        amount: Decimal,
+    },
+    DisputeResolved {
+        amount: Decimal,
+    },
+    ChargedBack {
+        amount: Decimal,
+    },
+    AccountLocked,
+    Rejected {
+        r#type: TransactionType,
+        rejection: Rejection,
+    },
+}
+
+// `before` and `after` are the client's balance around the row that caused the event;
+// a row can cause more than one event (a chargeback also locks the account).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineEvent {
+    pub seq: u64,
+    pub client: u16,
+    pub tx: u32,
+    pub timestamp: Option<u64>,
+    pub kind: EventKind,
+    pub before: Balance,
+    pub after: Balance,
+}
+
+impl EngineEvent {
+    pub fn to_change_output(&self) -> ChangeOutput {
+        ChangeOutput {
+            seq: self.seq,
+            client: self.client,
+            tx: self.tx,
+            old_available: self.before.available.round_dp(4),
+            old_held: self.before.held.round_dp(4),
+            old_locked: self.before.locked,
+            new_available: self.after.available.round_dp(4),
+            new_held: self.after.held.round_dp(4),
+            new_locked: self.after.locked,
+        }
+    }
+}
+
+pub trait EngineObserver: Send {
+    fn on_event(&mut self, event: &EngineEvent);
+}
+
+impl<F> EngineObserver for F
+where
+    F: FnMut(&EngineEvent) + Send,
+{
+    fn on_event(&mut self, event: &EngineEvent) {
+        self(event)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,93 @@
+use super::*;
+use crate::test_support::{new_tx, observed};
+use rust_decimal_macros::dec;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Keeps every event it is sent.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<EngineEvent>>>);
+
+impl Recorder {
+    fn events(&self) -> MutexGuard<'_, Vec<EngineEvent>> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl EngineObserver for Recorder {
+    fn on_event(&mut self, event: &EngineEvent) {
+        self.events().push(event.clone());
+    }
+}
+
+#[test]
+fn test_observer_receives_applied_events() {
+    let (mut engine, recorder) = observed(Recorder::default());
+
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))));
+    engine.process(new_tx(1, 1, TransactionType::Dispute, None));
+    engine.process(new_tx(1, 1, TransactionType::Resolve, None));
+
+    let events = recorder.events();
+    let kinds: Vec<&EventKind> = events.iter().map(|event| &event.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            &EventKind::Deposited { amount: dec!(10.0) },
+            &EventKind::DisputeOpened { amount: dec!(10.0) },
+            &EventKind::DisputeResolved { amount: dec!(10.0) },
+        ]
+    );
+    assert_eq!(events[1].seq, 1);
+    assert_eq!(events[1].before.available, dec!(10.0));
+    assert_eq!(events[1].after.held, dec!(10.0));
+}
+
+#[test]
+fn test_observer_receives_rejections() {
+    let (mut engine, recorder) = observed(Recorder::default());
+
+    engine.process(new_tx(1, 1, TransactionType::Withdrawal, Some(dec!(10.0))));
+
+    let events = recorder.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].kind,
+        EventKind::Rejected {
+            r#type: TransactionType::Withdrawal,
+            rejection: Rejection::InsufficientFunds,
+        }
+    );
+    assert_eq!(events[0].before, events[0].after);
+}
+
+#[test]
+fn test_chargeback_also_reports_account_locked() {
+    let (mut engine, recorder) = observed(Recorder::default());
+
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))));
+    engine.process(new_tx(1, 1, TransactionType::Dispute, None));
+    engine.process(new_tx(1, 1, TransactionType::Chargeback, None));
+
+    let events = recorder.events();
+    assert_eq!(events.len(), 4);
+    assert_eq!(
+        events[2].kind,
+        EventKind::ChargedBack { amount: dec!(10.0) }
+    );
+    assert_eq!(events[3].kind, EventKind::AccountLocked);
+    assert_eq!(events[3].seq, events[2].seq);
+    assert!(events[3].after.locked);
+}
+
+#[test]
+fn test_rejected_batch_notifies_nothing() {
+    let (mut engine, recorder) = observed(Recorder::default());
+
+    let result = engine.process_batch(vec![
+        new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))),
+        new_tx(1, 2, TransactionType::Withdrawal, Some(dec!(20.0))),
+    ]);
+
+    assert!(result.is_err());
+    assert!(recorder.events().is_empty());
+}
@@ -0,0 +1,115 @@
+use rust_decimal::Decimal;
+use serde::{Serialize, Serializer};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    Json,
+    Jsonl,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            _ => Err(format!("unknown output format `{format}`")),
+        }
+    }
+}
+
+// Amounts are written the way CSV output always wrote them: without trailing zeros but with
+// at least one decimal place, e.g. `100.0` and `1.5`. They are strings in JSON too, so every
+// digit is kept and both formats show the same text.
+pub(crate) fn serialize_amount<S: Serializer>(
+    amount: &Decimal,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let amount = amount.normalize();
+    match amount.scale() {
+        0 => serializer.collect_str(&format_args!("{amount}.0")),
+        _ => serializer.collect_str(&amount),
+    }
+}
+
+pub(crate) fn serialize_optional_amount<S: Serializer>(
+    amount: &Option<Decimal>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match amount {
+        Some(amount) => serialize_amount(amount, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+enum Sink<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Json { writer: W, rows: u64 },
+    Jsonl(W),
+}
+
+pub struct OutputWriter<W: Write> {
+    sink: Sink<W>,
+}
+
+impl<W: Write> OutputWriter<W> {
+    pub fn new(writer: W, format: OutputFormat) -> Self {
+        let sink = match format {
+            OutputFormat::Csv => Sink::Csv(Box::new(csv::Writer::from_writer(writer))),
+            OutputFormat::Json => Sink::Json { writer, rows: 0 },
+            OutputFormat::Jsonl => Sink::Jsonl(writer),
+        };
+
+        Self { sink }
+    }
+
+    pub fn write<T: Serialize>(&mut self, row: &T) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => writer.serialize(row)?,
+            Sink::Json { writer, rows } => {
+                writer.write_all(if *rows == 0 { b"[\n" } else { b",\n" })?;
+                serde_json::to_writer(&mut *writer, row)?;
+                *rows += 1;
+            }
+            Sink::Jsonl(writer) => {
+                serde_json::to_writer(&mut *writer, row)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Passes the rows written so far on, for a stream that is read while it is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => writer.flush(),
+            Sink::Json { writer, .. } | Sink::Jsonl(writer) => writer.flush(),
+        }
+    }
+
+    // A JSON array is only valid once it is closed, so this must be called after the last row.
+    pub fn finish(self) -> io::Result<W> {
+        match self.sink {
+            Sink::Csv(writer) => writer.into_inner().map_err(|e| e.into_error()),
+            Sink::Json { mut writer, rows } => {
+                writer.write_all(if rows == 0 { b"[]\n" } else { b"\n]\n" })?;
+                writer.flush()?;
+                Ok(writer)
+            }
+            Sink::Jsonl(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,116 @@
+use super::*;
+use crate::Engine;
+use crate::models::{AccountOutput, Balance, OpenDispute, TransactionInput, TransactionType};
+use rust_decimal_macros::dec;
+
+fn balance() -> Balance {
+    Balance {
+        available: dec!(1.1),
+        held: dec!(0.2),
+        locked: false,
+    }
+}
+
+fn render(format: OutputFormat, rows: &[AccountOutput]) -> String {
+    let mut writer = OutputWriter::new(Vec::new(), format);
+    for row in rows {
+        writer.write(row).unwrap();
+    }
+    String::from_utf8(writer.finish().unwrap()).unwrap()
+}
+
+#[test]
+fn test_csv_output() {
+    let output = render(OutputFormat::Csv, &[balance().to_output(1)]);
+
+    assert_eq!(
+        output,
+        "client,available,held,total,locked\n1,1.1,0.2,1.3,false\n"
+    );
+}
+
+#[test]
+fn test_large_decimals_keep_every_digit() {
+    let account = Balance {
+        available: dec!(123456789012345.6789),
+        held: dec!(0),
+        locked: false,
+    }
+    .to_output(1);
+
+    let csv = render(OutputFormat::Csv, std::slice::from_ref(&account));
+    let jsonl = render(OutputFormat::Jsonl, &[account]);
+
+    assert!(csv.ends_with("\n1,123456789012345.6789,0.0,123456789012345.6789,false\n"));
+    assert!(jsonl.contains("\"available\":\"123456789012345.6789\""));
+}
+
+#[test]
+fn test_json_writes_decimals_as_strings() {
+    let output = render(
+        OutputFormat::Json,
+        &[balance().to_output(1), balance().to_output(2)],
+    );
+
+    assert_eq!(
+        output,
+        "[\n\
+         {\"client\":1,\"available\":\"1.1\",\"held\":\"0.2\",\"total\":\"1.3\",\"locked\":false},\n\
+         {\"client\":2,\"available\":\"1.1\",\"held\":\"0.2\",\"total\":\"1.3\",\"locked\":false}\n\
+         ]\n"
+    );
+}
+
+#[test]
+fn test_json_without_rows_is_empty_array() {
+    assert_eq!(render(OutputFormat::Json, &[]), "[]\n");
+}
+
+#[test]
+fn test_jsonl_writes_one_object_per_line() {
+    let output = render(
+        OutputFormat::Jsonl,
+        &[balance().to_output(1), balance().to_output(2)],
+    );
+
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].starts_with("{\"client\":2,\"available\":\"1.1\""));
+}
+
+#[test]
+fn test_history_json_keeps_missing_amount() {
+    let mut engine = Engine::new();
+    engine.keep_history();
+    engine.process(TransactionInput {
+        r#type: TransactionType::Dispute,
+        client: 1,
+        tx: 9,
+        amount: None,
+        timestamp: None,
+    });
+
+    let mut writer = OutputWriter::new(Vec::new(), OutputFormat::Jsonl);
+    writer.write(&engine.history(1)[0].to_output()).unwrap();
+    let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+    assert!(output.contains("\"amount\":null"));
+    assert!(output.contains("\"outcome\":\"unknown_transaction\""));
+}
+
+#[test]
+fn test_open_dispute_json_writes_amount_as_string() {
+    let dispute = OpenDispute {
+        tx: 4,
+        client: 2,
+        amount: dec!(7.25),
+    };
+
+    let mut out = Vec::new();
+    serde_json::to_writer(&mut out, &dispute).unwrap();
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "{\"tx\":4,\"client\":2,\"amount\":\"7.25\"}"
+    );
+}
@@ -0,0 +1,616 @@
+use crate::input::{CsvDialect, InputError, InputFormat, TransactionReader};
+use crate::models::{Outcome, TransactionInput};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::{info, warn};
+
+#[cfg(unix)]
+use std::fs::{self, File, OpenOptions};
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+
+// Rows parsed ahead of the engine; a full queue slows every connection down to its pace.
+const QUEUE_LEN: usize = 1024;
+
+// How often the server looks at the shutdown flag while nothing happens.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// A client that stops reading its replies for this long is given up on, so it cannot hold
+// up the shutdown.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// What a client gets back for each row, on a line of its own and in the order sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ack {
+    Processed(Outcome),
+    Invalid(String),
+    // The server stopped applying rows after a failure, e.g. of the event log.
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub line: u64,
+    pub ack: Ack,
+}
+
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.ack {
+            Ack::Processed(Outcome::Applied) => write!(f, "{} applied", self.line),
+            Ack::Processed(Outcome::Rejected(rejection)) => {
+                write!(f, "{} rejected {}", self.line, rejection.as_str())
+            }
+            Ack::Invalid(message) => {
+                write!(f, "{} invalid {}", self.line, message.replace('\n', " "))
+            }
+            Ack::Failed => write!(f, "{} failed", self.line),
+        }
+    }
+}
+
+// Runs on the thread that called `Server::run`, so it can own the engine.
+pub trait RowHandler {
+    fn apply(&mut self, transaction: TransactionInput) -> io::Result<Outcome>;
+
+    // Called after every row, and every few milliseconds while none arrive.
+    fn tick(&mut self) {}
+}
+
+impl<F> RowHandler for F
+where
+    F: FnMut(TransactionInput) -> io::Result<Outcome>,
+{
+    fn apply(&mut self, transaction: TransactionInput) -> io::Result<Outcome> {
+        self(transaction)
+    }
+}
+
+struct Request {
+    line: u64,
+    row: Result<TransactionInput, String>,
+    replies: Sender<Reply>,
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Stream::Tcp(stream) => Stream::Tcp(stream.try_clone()?),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Stream::Unix(stream.try_clone()?),
+        })
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown(how),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+struct Connection {
+    stream: Stream,
+    handle: JoinHandle<()>,
+}
+
+// Takes rows from any number of TCP or Unix socket connections and named pipes, each
+// streaming CSV (with a header) or JSON Lines, and applies them all on the thread that
+// calls `run`. Rows of one stream are applied in the order sent; rows of different streams
+// in the order they arrive.
+pub struct Server {
+    listeners: Vec<Listener>,
+    #[cfg(unix)]
+    fifos: Vec<PathBuf>,
+    format: Option<InputFormat>,
+    dialect: CsvDialect,
+    acks: bool,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl Server {
+    // Without a `format`, each stream is read as JSON Lines if it starts with `{`.
+    pub fn new(format: Option<InputFormat>, dialect: CsvDialect) -> Self {
+        Self {
+            listeners: Vec::new(),
+            #[cfg(unix)]
+            fifos: Vec::new(),
+            format,
+            dialect,
+            acks: true,
+            shutdown: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn bind<A: ToSocketAddrs>(
+        addr: A,
+        format: Option<InputFormat>,
+        dialect: CsvDialect,
+    ) -> io::Result<Self> {
+        let mut server = Self::new(format, dialect);
+        server.listen_tcp(addr)?;
+        Ok(server)
+    }
+
+    pub fn listen_tcp<A: ToSocketAddrs>(&mut self, addr: A) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        self.listeners.push(Listener::Tcp(listener));
+        Ok(local_addr)
+    }
+
+    // A socket file left behind by a server that did not shut down cleanly is replaced.
+    #[cfg(unix)]
+    pub fn listen_unix(&mut self, path: &Path) -> io::Result<()> {
+        if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        self.listeners
+            .push(Listener::Unix(listener, path.to_path_buf()));
+        Ok(())
+    }
+
+    // Writers share one stream of lines, in CSV or JSON Lines, each ending its rows with a
+    // newline; a CSV header is only needed once. Replies cannot be sent back on a pipe.
+    #[cfg(unix)]
+    pub fn read_fifo(&mut self, path: &Path) -> io::Result<()> {
+        if !fs::metadata(path)?.file_type().is_fifo() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a named pipe", path.display()),
+            ));
+        }
+        self.fifos.push(path.to_path_buf());
+        Ok(())
+    }
+
+    // Without acknowledgements, clients only write; nothing is sent back on their connection.
+    pub fn set_acks(&mut self, acks: bool) {
+        self.acks = acks;
+    }
+
+    // The address of the first TCP listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listeners
+            .iter()
+            .find_map(|listener| match listener {
+                Listener::Tcp(listener) => Some(listener.local_addr()),
+                #[cfg(unix)]
+                Listener::Unix(..) => None,
+            })
+            .unwrap_or_else(|| Err(io::Error::other("not listening on TCP")))
+    }
+
+    // Setting the flag (e.g. from a signal handler) stops accepting connections, ends the
+    // ones open once the rows already sent are read, and makes `run` return.
+    pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.shutdown)
+    }
+
+    pub fn run<H: RowHandler>(self, mut handler: H) -> io::Result<()> {
+        let (requests, received) = mpsc::sync_channel(QUEUE_LEN);
+        let shutdown = Arc::clone(&self.shutdown);
+        let acceptor = thread::spawn(move || self.accept(requests));
+
+        let mut failure = None;
+        loop {
+            let request = match received.recv_timeout(POLL_INTERVAL) {
+                Ok(request) => request,
+                Err(RecvTimeoutError::Timeout) => {
+                    handler.tick();
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            let ack = match request.row {
+                Ok(_) if failure.is_some() => Ack::Failed,
+                Ok(transaction) => match handler.apply(transaction) {
+                    Ok(outcome) => Ack::Processed(outcome),
+                    Err(e) => {
+                        warn!(error = %e, "stopping the server after a failure");
+                        shutdown.store(true, Ordering::Relaxed);
+                        failure = Some(e);
+                        Ack::Failed
+                    }
+                },
+                Err(message) => Ack::Invalid(message),
+            };
+            // The client may be gone already; its other rows still count.
+            let _ = request.replies.send(Reply {
+                line: request.line,
+                ack,
+            });
+            handler.tick();
+        }
+
+        acceptor
+            .join()
+            .map_err(|_| io::Error::other("accept loop panicked"))??;
+
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn accept(self, requests: SyncSender<Request>) -> io::Result<()> {
+        for listener in &self.listeners {
+            match listener {
+                Listener::Tcp(listener) => listener.set_nonblocking(true)?,
+                #[cfg(unix)]
+                Listener::Unix(listener, _) => listener.set_nonblocking(true)?,
+            }
+        }
+        #[cfg(unix)]
+        let fifos: Vec<JoinHandle<()>> = self
+            .fifos
+            .iter()
+            .map(|path| self.spawn_fifo(path.clone(), requests.clone()))
+            .collect();
+        let mut connections: Vec<Connection> = Vec::new();
+
+        while !self.shutdown.load(Ordering::Relaxed) {
+            let mut accepted = false;
+            for listener in &self.listeners {
+                let (stream, peer) = match listener {
+                    Listener::Tcp(listener) => match listener.accept() {
+                        Ok((stream, peer)) => (Stream::Tcp(stream), peer.to_string()),
+                        Err(e) => {
+                            log_accept_error(e);
+                            continue;
+                        }
+                    },
+                    #[cfg(unix)]
+                    Listener::Unix(listener, path) => match listener.accept() {
+                        Ok((stream, _)) => (Stream::Unix(stream), path.display().to_string()),
+                        Err(e) => {
+                            log_accept_error(e);
+                            continue;
+                        }
+                    },
+                };
+                accepted = true;
+
+                match &stream {
+                    Stream::Tcp(stream) => {
+                        stream.set_nonblocking(false)?;
+                        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                    }
+                    #[cfg(unix)]
+                    Stream::Unix(stream) => {
+                        stream.set_nonblocking(false)?;
+                        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                    }
+                }
+                connections.retain(|connection| !connection.handle.is_finished());
+                connections.push(Connection {
+                    stream: stream.try_clone()?,
+                    handle: self.spawn_connection(stream, peer, requests.clone()),
+                });
+            }
+            if !accepted {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+
+        // Reads on a socket shut down for reading end as if the client had finished, so every
+        // connection sends its last rows and waits for their replies.
+        for connection in &connections {
+            let _ = connection.stream.shutdown(Shutdown::Read);
+        }
+        drop(requests);
+        for connection in connections {
+            let _ = connection.handle.join();
+        }
+        #[cfg(unix)]
+        for fifo in fifos {
+            let _ = fifo.join();
+        }
+        #[cfg(unix)]
+        for listener in &self.listeners {
+            if let Listener::Unix(_, path) = listener {
+                let _ = fs::remove_file(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn spawn_connection(
+        &self,
+        stream: Stream,
+        peer: String,
+        requests: SyncSender<Request>,
+    ) -> JoinHandle<()> {
+        let format = self.format;
+        let dialect = self.dialect.clone();
+        let acks = self.acks;
+
+        thread::spawn(move || {
+            info!(%peer, "connection opened");
+            match read_connection(stream, format, &dialect, acks, &requests) {
+                Ok(rows) => info!(%peer, rows, "connection closed"),
+                Err(e) => warn!(%peer, error = %e, "connection failed"),
+            }
+        })
+    }
+
+    #[cfg(unix)]
+    fn spawn_fifo(&self, path: PathBuf, requests: SyncSender<Request>) -> JoinHandle<()> {
+        let format = self.format;
+        let dialect = self.dialect.clone();
+        let shutdown = Arc::clone(&self.shutdown);
+
+        thread::spawn(move || {
+            if let Err(e) = read_fifo(&path, format, &dialect, &shutdown, &requests) {
+                warn!(fifo = %path.display(), error = %e, "named pipe failed");
+            }
+        })
+    }
+}
+
+fn log_accept_error(e: io::Error) {
+    if e.kind() != io::ErrorKind::WouldBlock {
+        warn!(error = %e, "failed to accept a connection");
+    }
+}
+
+fn read_connection(
+    mut stream: Stream,
+    format: Option<InputFormat>,
+    dialect: &CsvDialect,
+    acks: bool,
+    requests: &SyncSender<Request>,
+) -> io::Result<u64> {
+    let (replies, pending) = mpsc::channel();
+    let writer = match acks {
+        true => {
+            let writer_stream = stream.try_clone()?;
+            Some(thread::spawn(move || write_replies(writer_stream, pending)))
+        }
+        // Replies to a dropped receiver go nowhere.
+        false => None,
+    };
+
+    let count = read_rows(&mut stream, format, dialect, requests, &replies)?;
+
+    // The writer ends once the engine has replied to every row sent.
+    drop(replies);
+    match writer {
+        Some(writer) => writer
+            .join()
+            .map_err(|_| io::Error::other("reply writer panicked"))??,
+        // Tells the client every row was read.
+        None => stream.shutdown(Shutdown::Write)?,
+    }
+
+    Ok(count)
+}
+
+fn read_rows<R: Read>(
+    reader: R,
+    format: Option<InputFormat>,
+    dialect: &CsvDialect,
+    requests: &SyncSender<Request>,
+    replies: &Sender<Reply>,
+) -> io::Result<u64> {
+    let mut reader = BufReader::new(reader);
+    let format = match format {
+        Some(format) => format,
+        None => detect_format(&mut reader)?,
+    };
+    let mut rows: TransactionReader<_> = TransactionReader::with_dialect(reader, format, dialect)?;
+
+    let mut count = 0;
+    while let Some(row) = rows.next() {
+        let row = match row {
+            Ok(transaction) => Ok(transaction),
+            Err(InputError::Parse { message, .. }) => Err(message),
+            Err(InputError::Io(e)) => return Err(e),
+        };
+        let request = Request {
+            line: rows.line(),
+            row,
+            replies: replies.clone(),
+        };
+        if requests.send(request).is_err() {
+            break;
+        }
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn detect_format<R: BufRead>(reader: &mut R) -> io::Result<InputFormat> {
+    let buffer = reader.fill_buf()?;
+    Ok(
+        match buffer.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') => InputFormat::Jsonl,
+            _ => InputFormat::Csv,
+        },
+    )
+}
+
+fn write_replies(stream: Stream, pending: Receiver<Reply>) -> io::Result<()> {
+    let mut writer = BufWriter::new(stream.try_clone()?);
+
+    while let Ok(reply) = pending.recv() {
+        writeln!(writer, "{reply}")?;
+        // Replies that are already waiting go out in the same write.
+        while let Ok(reply) = pending.try_recv() {
+            writeln!(writer, "{reply}")?;
+        }
+        writer.flush()?;
+    }
+    drop(writer);
+
+    // Tells the client every reply has been sent.
+    stream.shutdown(Shutdown::Write)
+}
+
+// The pipe stays open for the whole run and is read as one stream of lines, so it does not
+// matter whether a writer opens it before or after the previous one closed it. Opened
+// without blocking, a read returns 0 while no writer is left and `WouldBlock` while one is
+// idle; both only mean waiting for more. Without a fixed format, each line is read by what
+// it looks like: a JSON object, the CSV header (the first one is kept and writers may repeat
+// it), or a CSV row under that header.
+#[cfg(unix)]
+fn read_fifo(
+    path: &Path,
+    format: Option<InputFormat>,
+    dialect: &CsvDialect,
+    shutdown: &AtomicBool,
+    requests: &SyncSender<Request>,
+) -> io::Result<()> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+    let mut reader = BufReader::new(FifoReader {
+        file: &file,
+        shutdown,
+    });
+    let (replies, _) = mpsc::channel();
+    let mut header: Option<Vec<u8>> = None;
+    let mut buffer = Vec::new();
+    let mut line = 0;
+
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+        line += 1;
+        let text = buffer.trim_ascii();
+        if text.is_empty() {
+            continue;
+        }
+
+        let row_format = format.unwrap_or(match text.first() {
+            Some(b'{') => InputFormat::Jsonl,
+            _ => InputFormat::Csv,
+        });
+        let row = match (row_format, &header) {
+            (InputFormat::Csv, None) if dialect.has_headers => {
+                header = Some(buffer.clone());
+                continue;
+            }
+            (InputFormat::Csv, Some(header)) if header.trim_ascii() == text => continue,
+            (InputFormat::Csv, Some(header)) if dialect.has_headers => {
+                read_row(header.chain(&buffer[..]), row_format, dialect)
+            }
+            _ => read_row(&buffer[..], row_format, dialect),
+        };
+        let row = match row {
+            Ok(transaction) => Ok(transaction),
+            Err(InputError::Parse { message, .. }) => Err(message),
+            Err(InputError::Io(e)) => return Err(e),
+        };
+        let request = Request {
+            line,
+            row,
+            replies: replies.clone(),
+        };
+        if requests.send(request).is_err() {
+            break;
+        }
+    }
+
+    info!(fifo = %path.display(), lines = line, "named pipe closed");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_row<R: Read>(
+    reader: R,
+    format: InputFormat,
+    dialect: &CsvDialect,
+) -> Result<TransactionInput, InputError> {
+    TransactionReader::with_dialect(reader, format, dialect)
+        .map_err(InputError::Io)?
+        .next()
+        .unwrap_or_else(|| {
+            Err(InputError::Parse {
+                line: 1,
+                message: "no row".to_string(),
+            })
+        })
+}
+
+// Reads until shutdown, however often writers come and go.
+#[cfg(unix)]
+struct FifoReader<'a> {
+    file: &'a File,
+    shutdown: &'a AtomicBool,
+}
+
+#[cfg(unix)]
+impl Read for FifoReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.shutdown.load(Ordering::Relaxed) {
+            match self.file.read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Ok(0) => thread::sleep(POLL_INTERVAL),
+                result => return result,
+            }
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests;
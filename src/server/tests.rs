@@ -0,0 +1,294 @@
+use super::*;
+use crate::Engine;
+use crate::models::Rejection;
+use rust_decimal_macros::dec;
+use std::io::Read;
+
+fn start() -> (SocketAddr, Arc<AtomicBool>, JoinHandle<Engine>) {
+    let server = Server::bind("127.0.0.1:0", None, CsvDialect::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let shutdown = server.shutdown_flag();
+
+    let handle = thread::spawn(move || {
+        let mut engine = Engine::new();
+        server
+            .run(|transaction| Ok(engine.process(transaction)))
+            .unwrap();
+        engine
+    });
+
+    (addr, shutdown, handle)
+}
+
+fn stop(shutdown: Arc<AtomicBool>, handle: JoinHandle<Engine>) -> Engine {
+    shutdown.store(true, Ordering::Relaxed);
+    handle.join().unwrap()
+}
+
+fn send(addr: SocketAddr, rows: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(rows.as_bytes()).unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+
+    let mut replies = String::new();
+    stream.read_to_string(&mut replies).unwrap();
+    replies
+}
+
+#[test]
+fn test_acknowledges_every_csv_row() {
+    let (addr, shutdown, handle) = start();
+
+    let replies = send(
+        addr,
+        "type,client,tx,amount\ndeposit,1,1,10.0\nwithdrawal,1,2,50.0\nbogus,1,3,1.0\n",
+    );
+    let engine = stop(shutdown, handle);
+
+    assert_eq!(
+        replies.lines().collect::<Vec<_>>()[..2],
+        ["2 applied", "3 rejected insufficient_funds"]
+    );
+    assert!(replies.lines().nth(2).unwrap().starts_with("4 invalid "));
+    assert_eq!(engine.get_accounts()[&1].available, dec!(10.0));
+}
+
+#[test]
+fn test_reads_json_lines_connection() {
+    let (addr, shutdown, handle) = start();
+
+    let replies = send(
+        addr,
+        "{\"type\":\"deposit\",\"client\":2,\"tx\":1,\"amount\":\"3.5\"}\n\
+         {\"type\":\"dispute\",\"client\":2,\"tx\":1}\n",
+    );
+    let engine = stop(shutdown, handle);
+
+    assert_eq!(replies, "1 applied\n2 applied\n");
+    assert_eq!(engine.get_accounts()[&2].held, dec!(3.5));
+}
+
+#[test]
+fn test_feeds_concurrent_connections_into_one_engine() {
+    let (addr, shutdown, handle) = start();
+
+    let clients: Vec<_> = (0..4u16)
+        .map(|client| {
+            thread::spawn(move || {
+                let mut rows = String::from("type,client,tx,amount\n");
+                for i in 0..50u32 {
+                    rows.push_str(&format!(
+                        "deposit,{client},{},1.0\n",
+                        u32::from(client) * 1000 + i
+                    ));
+                }
+                send(addr, &rows)
+            })
+        })
+        .collect();
+    for client in clients {
+        assert_eq!(client.join().unwrap().lines().count(), 50);
+    }
+    let engine = stop(shutdown, handle);
+
+    assert_eq!(engine.get_accounts().len(), 4);
+    for account in engine.get_accounts().values() {
+        assert_eq!(account.available, dec!(50.0));
+    }
+}
+
+#[test]
+fn test_shutdown_ends_open_connections() {
+    let (addr, shutdown, handle) = start();
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"type,client,tx,amount\ndeposit,1,1,2.0\n")
+        .unwrap();
+    let mut reply = [0; 10];
+    stream.read_exact(&mut reply).unwrap();
+
+    let engine = stop(shutdown, handle);
+    let mut rest = String::new();
+    stream.read_to_string(&mut rest).unwrap();
+
+    assert_eq!(&reply, b"2 applied\n");
+    assert_eq!(rest, "");
+    assert_eq!(engine.get_accounts()[&1].available, dec!(2.0));
+}
+
+#[test]
+fn test_failure_stops_applying_rows() {
+    let server =
+        Server::bind("127.0.0.1:0", Some(InputFormat::Csv), CsvDialect::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        server.run(|transaction: TransactionInput| match transaction.tx {
+            1 => Ok(Outcome::Rejected(Rejection::Duplicate)),
+            _ => Err(io::Error::other("disk full")),
+        })
+    });
+
+    let replies = send(
+        addr,
+        "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,1,2,1.0\ndeposit,1,3,1.0\n",
+    );
+
+    assert_eq!(replies, "2 rejected duplicate\n3 failed\n4 failed\n");
+    assert!(handle.join().unwrap().is_err());
+}
+
+struct Counting {
+    engine: Engine,
+    ticks: Arc<AtomicBool>,
+}
+
+impl RowHandler for Counting {
+    fn apply(&mut self, transaction: TransactionInput) -> io::Result<Outcome> {
+        Ok(self.engine.process(transaction))
+    }
+
+    fn tick(&mut self) {
+        self.ticks.store(true, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn test_ticks_while_idle() {
+    let server = Server::bind("127.0.0.1:0", None, CsvDialect::default()).unwrap();
+    let shutdown = server.shutdown_flag();
+    let ticks = Arc::new(AtomicBool::new(false));
+    let handler = Counting {
+        engine: Engine::new(),
+        ticks: Arc::clone(&ticks),
+    };
+    let handle = thread::spawn(move || server.run(handler));
+
+    while !ticks.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    shutdown.store(true, Ordering::Relaxed);
+    handle.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_without_acks_sends_nothing_back() {
+    let mut server = Server::bind("127.0.0.1:0", None, CsvDialect::default()).unwrap();
+    server.set_acks(false);
+    let addr = server.local_addr().unwrap();
+    let shutdown = server.shutdown_flag();
+    let handle = thread::spawn(move || {
+        let mut engine = Engine::new();
+        server
+            .run(|transaction| Ok(engine.process(transaction)))
+            .unwrap();
+        engine
+    });
+
+    let replies = send(addr, "type,client,tx,amount\ndeposit,1,1,4.0\n");
+    let engine = stop(shutdown, handle);
+
+    assert_eq!(replies, "");
+    assert_eq!(engine.get_accounts()[&1].available, dec!(4.0));
+}
+
+#[cfg(unix)]
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("server-{}-{name}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reads_unix_socket_and_removes_it() {
+    let path = temp_path("unix.sock");
+    // A file left behind by an earlier run.
+    drop(UnixListener::bind(&path).unwrap());
+
+    let mut server = Server::new(None, CsvDialect::default());
+    server.listen_unix(&path).unwrap();
+    let shutdown = server.shutdown_flag();
+    let handle = thread::spawn(move || {
+        let mut engine = Engine::new();
+        server
+            .run(|transaction| Ok(engine.process(transaction)))
+            .unwrap();
+        engine
+    });
+
+    let mut stream = UnixStream::connect(&path).unwrap();
+    stream
+        .write_all(b"type,client,tx,amount\ndeposit,3,1,1.5\n")
+        .unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    let mut replies = String::new();
+    stream.read_to_string(&mut replies).unwrap();
+    let engine = stop(shutdown, handle);
+
+    assert_eq!(replies, "2 applied\n");
+    assert_eq!(engine.get_accounts()[&3].available, dec!(1.5));
+    assert!(!path.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reads_fifo_writers_as_one_stream() {
+    let path = temp_path("rows.fifo");
+    let c_path = std::ffi::CString::new(path.as_os_str().as_encoded_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+    let mut server = Server::new(None, CsvDialect::default());
+    server.read_fifo(&path).unwrap();
+    let shutdown = server.shutdown_flag();
+    let (applied, rows) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        let mut engine = Engine::new();
+        server
+            .run(|transaction| {
+                let outcome = engine.process(transaction);
+                applied.send(()).unwrap();
+                Ok(outcome)
+            })
+            .unwrap();
+        engine
+    });
+
+    // Opening for writing blocks until the server has the pipe open for reading.
+    let mut writer = OpenOptions::new().write(true).open(&path).unwrap();
+    writer
+        .write_all(b"type,client,tx,amount\ndeposit,5,1,2.0\n")
+        .unwrap();
+    drop(writer);
+    rows.recv_timeout(Duration::from_secs(10)).unwrap();
+    // Both of these may open the pipe before the server saw the writer above close it.
+    let mut writer = OpenOptions::new().write(true).open(&path).unwrap();
+    writer
+        .write_all(b"{\"type\":\"withdrawal\",\"client\":5,\"tx\":2,\"amount\":\"0.5\"}\n")
+        .unwrap();
+    drop(writer);
+    let mut writer = OpenOptions::new().write(true).open(&path).unwrap();
+    writer
+        .write_all(b"type,client,tx,amount\ndeposit,5,3,1.0\n")
+        .unwrap();
+    drop(writer);
+    rows.recv_timeout(Duration::from_secs(10)).unwrap();
+    rows.recv_timeout(Duration::from_secs(10)).unwrap();
+    let engine = stop(shutdown, handle);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(engine.get_accounts()[&5].available, dec!(2.5));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_rejects_a_regular_file_as_fifo() {
+    let path = temp_path("regular");
+    fs::write(&path, "").unwrap();
+
+    let mut server = Server::new(None, CsvDialect::default());
+    let error = server.read_fifo(&path).unwrap_err();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,158 @@
+use crate::codec::{
+    invalid_data, read_bool, read_decimal, read_u8, read_u16, read_u32, read_u64, write_bool,
+    write_decimal, write_len,
+};
+use crate::ledger::{Ledger, LedgerAccount};
+use crate::models::{Account, Deposit, Transaction};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"TXES";
+const VERSION: u16 = 1;
+
+const TAG_DEPOSIT: u8 = 0;
+const TAG_WITHDRAWAL: u8 = 1;
+
+const TAG_CLIENT_AVAILABLE: u8 = 0;
+const TAG_CLIENT_HELD: u8 = 1;
+const TAG_SETTLEMENT: u8 = 2;
+const TAG_CHARGEBACK_LOSSES: u8 = 3;
+
+pub(crate) struct EngineState {
+    pub(crate) accounts: HashMap<u16, Account>,
+    pub(crate) transactions: HashMap<u32, Transaction>,
+    pub(crate) ledger: Ledger,
+    pub(crate) next_seq: u64,
+}
+
+pub(crate) fn write<W: Write>(
+    writer: &mut W,
+    accounts: &HashMap<u16, Account>,
+    transactions: &HashMap<u32, Transaction>,
+    ledger: &Ledger,
+    next_seq: u64,
+) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+
+    write_len(writer, accounts.len())?;
+    for (&client_id, account) in accounts {
+        writer.write_all(&client_id.to_le_bytes())?;
+        write_decimal(writer, account.available)?;
+        write_decimal(writer, account.held)?;
+        write_bool(writer, account.locked)?;
+    }
+
+    write_len(writer, transactions.len())?;
+    for (&tx_id, transaction) in transactions {
+        writer.write_all(&tx_id.to_le_bytes())?;
+        match transaction {
+            Transaction::Deposit(deposit) => {
+                writer.write_all(&[TAG_DEPOSIT])?;
+                writer.write_all(&deposit.client_id.to_le_bytes())?;
+                write_decimal(writer, deposit.amount)?;
+                write_bool(writer, deposit.under_dispute)?;
+            }
+            Transaction::Withdrawal => writer.write_all(&[TAG_WITHDRAWAL])?,
+        }
+    }
+
+    write_len(writer, ledger.balances().len())?;
+    for (&account, &balance) in ledger.balances() {
+        write_ledger_account(writer, account)?;
+        write_decimal(writer, balance)?;
+    }
+
+    writer.write_all(&next_seq.to_le_bytes())?;
+
+    writer.flush()
+}
+
+pub(crate) fn read<R: Read>(reader: &mut R) -> io::Result<EngineState> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not an engine snapshot"));
+    }
+
+    let version = read_u16(reader)?;
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "unsupported snapshot version {version}"
+        )));
+    }
+
+    let accounts_len = read_u64(reader)?;
+    let mut accounts = HashMap::new();
+    for _ in 0..accounts_len {
+        let client_id = read_u16(reader)?;
+        let account = Account {
+            available: read_decimal(reader)?,
+            held: read_decimal(reader)?,
+            locked: read_bool(reader)?,
+        };
+        accounts.insert(client_id, account);
+    }
+
+    let transactions_len = read_u64(reader)?;
+    let mut transactions = HashMap::new();
+    for _ in 0..transactions_len {
+        let tx = read_u32(reader)?;
+        let transaction = match read_u8(reader)? {
+            TAG_DEPOSIT => Transaction::Deposit(Deposit {
+                tx,
+                client_id: read_u16(reader)?,
+                amount: read_decimal(reader)?,
+                under_dispute: read_bool(reader)?,
+            }),
+            TAG_WITHDRAWAL => Transaction::Withdrawal,
+            tag => return Err(invalid_data(format!("unknown transaction tag {tag}"))),
+        };
+        transactions.insert(tx, transaction);
+    }
+
+    let balances_len = read_u64(reader)?;
+    let mut balances = HashMap::new();
+    for _ in 0..balances_len {
+        let account = read_ledger_account(reader)?;
+        balances.insert(account, read_decimal(reader)?);
+    }
+    let ledger = Ledger::from_balances(balances);
+
+    let next_seq = read_u64(reader)?;
+
+    Ok(EngineState {
+        accounts,
+        transactions,
+        ledger,
+        next_seq,
+    })
+}
+
+fn write_ledger_account<W: Write>(writer: &mut W, account: LedgerAccount) -> io::Result<()> {
+    let (tag, client_id) = match account {
+        LedgerAccount::ClientAvailable(client_id) => (TAG_CLIENT_AVAILABLE, client_id),
+        LedgerAccount::ClientHeld(client_id) => (TAG_CLIENT_HELD, client_id),
+        LedgerAccount::Settlement => (TAG_SETTLEMENT, 0),
+        LedgerAccount::ChargebackLosses => (TAG_CHARGEBACK_LOSSES, 0),
+    };
+
+    writer.write_all(&[tag])?;
+    writer.write_all(&client_id.to_le_bytes())
+}
+
+fn read_ledger_account<R: Read>(reader: &mut R) -> io::Result<LedgerAccount> {
+    let tag = read_u8(reader)?;
+    let client_id = read_u16(reader)?;
+
+    match tag {
+        TAG_CLIENT_AVAILABLE => Ok(LedgerAccount::ClientAvailable(client_id)),
+        TAG_CLIENT_HELD => Ok(LedgerAccount::ClientHeld(client_id)),
+        TAG_SETTLEMENT => Ok(LedgerAccount::Settlement),
+        TAG_CHARGEBACK_LOSSES => Ok(LedgerAccount::ChargebackLosses),
+        tag => Err(invalid_data(format!("unknown ledger account tag {tag}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,108 @@
+use crate::Engine;
+use crate::ledger::LedgerAccount;
+use crate::models::TransactionType;
+use crate::test_support::new_tx;
+use rust_decimal_macros::dec;
+
+fn round_trip(engine: &Engine) -> Engine {
+    let mut buffer = Vec::new();
+    engine.snapshot(&mut buffer).unwrap();
+    Engine::restore(buffer.as_slice()).unwrap()
+}
+
+#[test]
+fn test_restore_keeps_account_balances() {
+    let mut engine = Engine::new();
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.5))));
+    engine.process(new_tx(2, 2, TransactionType::Deposit, Some(dec!(3.1234))));
+    engine.process(new_tx(2, 3, TransactionType::Withdrawal, Some(dec!(1.0))));
+
+    let restored = round_trip(&engine);
+
+    let first = restored.get_accounts().get(&1).unwrap();
+    assert_eq!(first.available, dec!(10.5));
+    let second = restored.get_accounts().get(&2).unwrap();
+    assert_eq!(second.available, dec!(2.1234));
+    assert!(!second.locked);
+}
+
+#[test]
+fn test_restore_keeps_dispute_state() {
+    let mut engine = Engine::new();
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(100.0))));
+    engine.process(new_tx(1, 1, TransactionType::Dispute, None));
+
+    let mut restored = round_trip(&engine);
+    restored.process(new_tx(1, 1, TransactionType::Chargeback, None));
+
+    let account = restored.get_accounts().get(&1).unwrap();
+    assert_eq!(account.available, dec!(0.0));
+    assert_eq!(account.held, dec!(0.0));
+    assert!(account.locked);
+}
+
+#[test]
+fn test_restore_keeps_duplicate_detection() {
+    let mut engine = Engine::new();
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(100.0))));
+    engine.process(new_tx(1, 2, TransactionType::Withdrawal, Some(dec!(40.0))));
+
+    let mut restored = round_trip(&engine);
+    restored.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(100.0))));
+    restored.process(new_tx(1, 2, TransactionType::Withdrawal, Some(dec!(40.0))));
+
+    let account = restored.get_accounts().get(&1).unwrap();
+    assert_eq!(account.available, dec!(60.0));
+}
+
+#[test]
+fn test_restore_rejects_unknown_format() {
+    let result = Engine::restore(&b"NOPE\x01\x00"[..]);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_restore_rejects_unsupported_version() {
+    let mut buffer = Vec::new();
+    Engine::new().snapshot(&mut buffer).unwrap();
+    buffer[4] = 0xFF;
+
+    let result = Engine::restore(buffer.as_slice());
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_restore_keeps_ledger_balances() {
+    let mut engine = Engine::new();
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(100.0))));
+    engine.process(new_tx(1, 1, TransactionType::Dispute, None));
+    engine.process(new_tx(1, 1, TransactionType::Chargeback, None));
+
+    let restored = round_trip(&engine);
+
+    assert!(restored.check_ledger().is_ok());
+    assert_eq!(
+        restored.ledger().balance(LedgerAccount::ChargebackLosses),
+        dec!(100.0)
+    );
+}
+
+#[test]
+fn test_snapshot_leaves_out_history() {
+    let mut engine = Engine::new();
+    engine.keep_history();
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))));
+    engine.process(new_tx(1, 2, TransactionType::Withdrawal, Some(dec!(50.0))));
+
+    let mut restored = round_trip(&engine);
+    assert!(!restored.keeps_history());
+    restored.keep_history();
+    restored.process(new_tx(1, 3, TransactionType::Withdrawal, Some(dec!(5.0))));
+
+    let history = restored.history(1);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].seq, 2);
+    assert_eq!(history[0].balance.available, dec!(5.0));
+}
@@ -0,0 +1,189 @@
+use crate::Engine;
+use crate::models::TransactionType;
+use crate::observer::{EngineEvent, EngineObserver, EventKind};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+const TYPES: [TransactionType; 5] = [
+    TransactionType::Deposit,
+    TransactionType::Withdrawal,
+    TransactionType::Dispute,
+    TransactionType::Resolve,
+    TransactionType::Chargeback,
+];
+
+// Rows of one type; `volume` sums the amounts of the applied ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TypeStats {
+    pub r#type: &'static str,
+    pub rows: u64,
+    pub applied: u64,
+    pub volume: Decimal,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DisputeStats {
+    pub opened: u64,
+    pub resolved: u64,
+    pub charged_back: u64,
+}
+
+// Counts cover the rows of this run only; `accounts` and `held` describe the engine at the
+// time of the report, including any state it was loaded from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StatsReport {
+    pub rows_read: u64,
+    pub parse_failures: u64,
+    pub transactions: Vec<TypeStats>,
+    pub applied: u64,
+    pub rejected: BTreeMap<&'static str, u64>,
+    pub disputes: DisputeStats,
+    pub accounts_locked: u64,
+    pub accounts: u64,
+    pub held: Decimal,
+}
+
+impl StatsReport {
+    pub fn rejected_total(&self) -> u64 {
+        self.rejected.values().sum()
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rows read: {}", self.rows_read)?;
+        writeln!(f, "parse failures: {}", self.parse_failures)?;
+        for stats in &self.transactions {
+            writeln!(
+                f,
+                "{}: {} rows, {} applied, volume {}",
+                stats.r#type, stats.rows, stats.applied, stats.volume
+            )?;
+        }
+        writeln!(f, "applied: {}", self.applied)?;
+        writeln!(f, "rejected: {}", self.rejected_total())?;
+        for (reason, count) in &self.rejected {
+            writeln!(f, "  {reason}: {count}")?;
+        }
+        writeln!(f, "disputes opened: {}", self.disputes.opened)?;
+        writeln!(f, "disputes resolved: {}", self.disputes.resolved)?;
+        writeln!(f, "disputes charged back: {}", self.disputes.charged_back)?;
+        writeln!(f, "accounts locked: {}", self.accounts_locked)?;
+        writeln!(f, "accounts: {}", self.accounts)?;
+        writeln!(f, "held: {}", self.held)
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    parse_failures: u64,
+    // Indexed like `TYPES`.
+    transactions: [TypeStats; TYPES.len()],
+    rejected: BTreeMap<&'static str, u64>,
+    accounts_locked: u64,
+}
+
+// Counts what the engine did over a run. The engine owns the clone it was given, so the
+// counters sit behind an `Arc` and `report` can read them through any other clone.
+#[derive(Clone, Default)]
+pub struct RunStats {
+    counters: Arc<Mutex<Counters>>,
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn counters(&self) -> MutexGuard<'_, Counters> {
+        self.counters
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Rows that never reach the engine are only known to the reader.
+    pub fn parse_failed(&self) {
+        self.counters().parse_failures += 1;
+    }
+
+    pub fn report(&self, engine: &Engine) -> StatsReport {
+        let counters = self.counters();
+
+        let transactions: Vec<TypeStats> = TYPES
+            .iter()
+            .zip(&counters.transactions)
+            .map(|(r#type, stats)| TypeStats {
+                r#type: r#type.as_str(),
+                ..stats.clone()
+            })
+            .collect();
+        let rows: u64 = transactions.iter().map(|stats| stats.rows).sum();
+        let applied: u64 = transactions.iter().map(|stats| stats.applied).sum();
+        let applied_of = |r#type: TransactionType| transactions[index(r#type)].applied;
+
+        StatsReport {
+            rows_read: rows + counters.parse_failures,
+            parse_failures: counters.parse_failures,
+            applied,
+            rejected: counters.rejected.clone(),
+            disputes: DisputeStats {
+                opened: applied_of(TransactionType::Dispute),
+                resolved: applied_of(TransactionType::Resolve),
+                charged_back: applied_of(TransactionType::Chargeback),
+            },
+            accounts_locked: counters.accounts_locked,
+            accounts: engine.get_accounts().len() as u64,
+            held: engine
+                .get_accounts()
+                .values()
+                .map(|account| account.held)
+                .sum(),
+            transactions,
+        }
+    }
+}
+
+fn index(r#type: TransactionType) -> usize {
+    match r#type {
+        TransactionType::Deposit => 0,
+        TransactionType::Withdrawal => 1,
+        TransactionType::Dispute => 2,
+        TransactionType::Resolve => 3,
+        TransactionType::Chargeback => 4,
+    }
+}
+
+impl EngineObserver for RunStats {
+    fn on_event(&mut self, event: &EngineEvent) {
+        let mut counters = self.counters();
+
+        let (r#type, amount) = match &event.kind {
+            EventKind::Deposited { amount } => (TransactionType::Deposit, *amount),
+            EventKind::Withdrawn { amount } => (TransactionType::Withdrawal, *amount),
+            EventKind::DisputeOpened { amount } => (TransactionType::Dispute, *amount),
+            EventKind::DisputeResolved { amount } => (TransactionType::Resolve, *amount),
+            EventKind::ChargedBack { amount } => (TransactionType::Chargeback, *amount),
+            EventKind::Rejected { r#type, rejection } => {
+                counters.transactions[index(*r#type)].rows += 1;
+                *counters.rejected.entry(rejection.as_str()).or_default() += 1;
+                return;
+            }
+            // Follows the chargeback that caused it, which was already counted as a row.
+            EventKind::AccountLocked => {
+                counters.accounts_locked += 1;
+                return;
+            }
+        };
+
+        let stats = &mut counters.transactions[index(r#type)];
+        stats.rows += 1;
+        stats.applied += 1;
+        stats.volume += amount;
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,66 @@
+use super::*;
+use crate::test_support::{new_tx, observed};
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_counts_rows_and_volumes_by_type() {
+    let (mut engine, stats) = observed(RunStats::new());
+
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))));
+    engine.process(new_tx(2, 2, TransactionType::Deposit, Some(dec!(5.5))));
+    engine.process(new_tx(1, 3, TransactionType::Withdrawal, Some(dec!(2.0))));
+    engine.process(new_tx(1, 4, TransactionType::Withdrawal, Some(dec!(50.0))));
+    stats.parse_failed();
+
+    let report = stats.report(&engine);
+
+    assert_eq!(report.rows_read, 5);
+    assert_eq!(report.parse_failures, 1);
+    assert_eq!(report.applied, 3);
+    assert_eq!(report.transactions[0].r#type, "deposit");
+    assert_eq!(report.transactions[0].rows, 2);
+    assert_eq!(report.transactions[0].volume, dec!(15.5));
+    assert_eq!(report.transactions[1].rows, 2);
+    assert_eq!(report.transactions[1].applied, 1);
+    assert_eq!(report.transactions[1].volume, dec!(2.0));
+    assert_eq!(report.rejected["insufficient_funds"], 1);
+    assert_eq!(report.accounts, 2);
+}
+
+#[test]
+fn test_counts_disputes_and_locked_accounts() {
+    let (mut engine, stats) = observed(RunStats::new());
+
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(10.0))));
+    engine.process(new_tx(1, 2, TransactionType::Deposit, Some(dec!(4.0))));
+    engine.process(new_tx(1, 1, TransactionType::Dispute, None));
+    engine.process(new_tx(1, 1, TransactionType::Chargeback, None));
+    engine.process(new_tx(1, 2, TransactionType::Dispute, None));
+    engine.process(new_tx(1, 9, TransactionType::Resolve, None));
+
+    let report = stats.report(&engine);
+
+    assert_eq!(report.rows_read, 6);
+    assert_eq!(report.disputes.opened, 2);
+    assert_eq!(report.disputes.resolved, 0);
+    assert_eq!(report.disputes.charged_back, 1);
+    assert_eq!(report.accounts_locked, 1);
+    assert_eq!(report.held, dec!(4.0));
+    assert_eq!(report.rejected_total(), 1);
+}
+
+#[test]
+fn test_report_as_json_and_text() {
+    let (mut engine, stats) = observed(RunStats::new());
+    engine.process(new_tx(1, 1, TransactionType::Deposit, Some(dec!(1.5))));
+
+    let report = stats.report(&engine);
+    let json = serde_json::to_value(&report).unwrap();
+    let text = report.to_string();
+
+    assert_eq!(json["transactions"][0]["volume"], "1.5");
+    assert_eq!(json["held"], "0");
+    assert_eq!(json["rows_read"], 1);
+    assert!(text.contains("deposit: 1 rows, 1 applied, volume 1.5\n"));
+    assert!(text.starts_with("rows read: 1\n"));
+}
@@ -0,0 +1,26 @@
+use crate::Engine;
+use crate::models::{TransactionInput, TransactionType};
+use crate::observer::EngineObserver;
+use rust_decimal::Decimal;
+
+pub(crate) fn new_tx(
+    client: u16,
+    tx: u32,
+    transaction_type: TransactionType,
+    amount: Option<Decimal>,
+) -> TransactionInput {
+    TransactionInput {
+        r#type: transaction_type,
+        client,
+        tx,
+        amount,
+        timestamp: None,
+    }
+}
+
+// An engine with `observer` subscribed, and a handle on what it observes.
+pub(crate) fn observed<O: EngineObserver + Clone + 'static>(observer: O) -> (Engine, O) {
+    let mut engine = Engine::new();
+    engine.subscribe(observer.clone());
+    (engine, observer)
+}
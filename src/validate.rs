@@ -0,0 +1,179 @@
+use crate::input::{CsvDialect, InputError, InputFormat, TransactionReader};
+use crate::models::TransactionType;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io::{self, Read};
+
+const MAX_AMOUNT_DECIMALS: u32 = 4;
+
+// The type is kept as text so an unknown type is reported as such rather than as a row
+// that does not parse.
+#[derive(Deserialize)]
+struct RawTransaction {
+    #[serde(rename = "type")]
+    r#type: String,
+    #[serde(rename = "client")]
+    _client: u16,
+    tx: u32,
+    amount: Option<Decimal>,
+    #[serde(rename = "timestamp", default)]
+    _timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    Schema(String),
+    UnknownType(String),
+    MissingAmount,
+    UnexpectedAmount,
+    OverPrecision(Decimal),
+    DuplicateTx(u32),
+    UnknownReference(u32),
+}
+
+impl Issue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Issue::Schema(_) => "schema_error",
+            Issue::UnknownType(_) => "unknown_type",
+            Issue::MissingAmount => "missing_amount",
+            Issue::UnexpectedAmount => "unexpected_amount",
+            Issue::OverPrecision(_) => "over_precision",
+            Issue::DuplicateTx(_) => "duplicate_tx",
+            Issue::UnknownReference(_) => "unknown_reference",
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Schema(message) => write!(f, "{message}"),
+            Issue::UnknownType(name) => write!(f, "unknown transaction type `{name}`"),
+            Issue::MissingAmount => write!(f, "deposits and withdrawals need an amount"),
+            Issue::UnexpectedAmount => {
+                write!(f, "disputes, resolves and chargebacks take no amount")
+            }
+            Issue::OverPrecision(amount) => write!(
+                f,
+                "amount {amount} has more than {MAX_AMOUNT_DECIMALS} decimal places"
+            ),
+            Issue::DuplicateTx(tx) => write!(f, "transaction {tx} was already seen"),
+            Issue::UnknownReference(tx) => {
+                write!(
+                    f,
+                    "transaction {tx} is not an earlier deposit or withdrawal"
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub line: u64,
+    pub issue: Issue,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub rows: u64,
+    pub rows_with_issues: u64,
+    pub issues: BTreeMap<&'static str, u64>,
+}
+
+impl Summary {
+    pub fn is_clean(&self) -> bool {
+        self.rows_with_issues == 0
+    }
+}
+
+// Checks rows the way the engine would read them, without touching any balances. Ids are
+// remembered across inputs, so a set of daily files can be validated as one run.
+#[derive(Default)]
+pub struct Validator {
+    seen: HashSet<u32>,
+    summary: Summary,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn summary(&self) -> &Summary {
+        &self.summary
+    }
+
+    pub fn validate<R: Read>(
+        &mut self,
+        reader: R,
+        format: InputFormat,
+        dialect: &CsvDialect,
+        mut report: impl FnMut(Finding),
+    ) -> io::Result<()> {
+        let mut reader: TransactionReader<R, RawTransaction> =
+            TransactionReader::with_dialect(reader, format, dialect)?;
+
+        while let Some(row) = reader.next() {
+            let issues = match row {
+                Ok(transaction) => self.check(transaction),
+                Err(InputError::Io(e)) => return Err(e),
+                Err(InputError::Parse { message, .. }) => vec![Issue::Schema(message)],
+            };
+
+            self.summary.rows += 1;
+            if !issues.is_empty() {
+                self.summary.rows_with_issues += 1;
+            }
+            for issue in issues {
+                *self.summary.issues.entry(issue.as_str()).or_default() += 1;
+                report(Finding {
+                    line: reader.line(),
+                    issue,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check(&mut self, transaction: RawTransaction) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        let Ok(r#type) = transaction.r#type.parse::<TransactionType>() else {
+            issues.push(Issue::UnknownType(transaction.r#type));
+            return issues;
+        };
+
+        match r#type {
+            TransactionType::Deposit | TransactionType::Withdrawal => {
+                match transaction.amount {
+                    None => issues.push(Issue::MissingAmount),
+                    Some(amount) if amount.normalize().scale() > MAX_AMOUNT_DECIMALS => {
+                        issues.push(Issue::OverPrecision(amount))
+                    }
+                    Some(_) => {}
+                }
+                if !self.seen.insert(transaction.tx) {
+                    issues.push(Issue::DuplicateTx(transaction.tx));
+                }
+            }
+            TransactionType::Dispute | TransactionType::Resolve | TransactionType::Chargeback => {
+                if transaction.amount.is_some() {
+                    issues.push(Issue::UnexpectedAmount);
+                }
+                if !self.seen.contains(&transaction.tx) {
+                    issues.push(Issue::UnknownReference(transaction.tx));
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests;
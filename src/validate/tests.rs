@@ -0,0 +1,108 @@
+use super::*;
+use rust_decimal_macros::dec;
+
+fn validate_csv(validator: &mut Validator, data: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    validator
+        .validate(
+            data.as_bytes(),
+            InputFormat::Csv,
+            &CsvDialect::default(),
+            |finding| findings.push(finding),
+        )
+        .unwrap();
+    findings
+}
+
+fn issues(data: &str) -> Vec<(u64, Issue)> {
+    validate_csv(&mut Validator::new(), data)
+        .into_iter()
+        .map(|finding| (finding.line, finding.issue))
+        .collect()
+}
+
+#[test]
+fn test_clean_file_has_no_findings() {
+    let mut validator = Validator::new();
+    let findings = validate_csv(
+        &mut validator,
+        "type,client,tx,amount\n\
+         deposit,1,1,1.5\n\
+         withdrawal,1,2,0.5\n\
+         dispute,1,1,\n\
+         resolve,1,1,\n",
+    );
+
+    assert!(findings.is_empty());
+    assert_eq!(validator.summary().rows, 4);
+    assert!(validator.summary().is_clean());
+}
+
+#[test]
+fn test_reports_unknown_type_and_schema_errors() {
+    let found = issues(
+        "type,client,tx,amount\n\
+         refund,1,1,1.0\n\
+         deposit,abc,2,1.0\n",
+    );
+
+    assert_eq!(found[0], (2, Issue::UnknownType(String::from("refund"))));
+    assert_eq!(found[1].0, 3);
+    assert!(matches!(found[1].1, Issue::Schema(_)));
+}
+
+#[test]
+fn test_reports_amount_problems() {
+    let found = issues(
+        "type,client,tx,amount\n\
+         deposit,1,1,\n\
+         deposit,1,2,1.23456\n\
+         deposit,1,3,1.23450\n\
+         dispute,1,3,1.0\n",
+    );
+
+    assert_eq!(
+        found,
+        vec![
+            (2, Issue::MissingAmount),
+            (3, Issue::OverPrecision(dec!(1.23456))),
+            (5, Issue::UnexpectedAmount),
+        ]
+    );
+}
+
+#[test]
+fn test_reports_duplicates_and_unknown_references() {
+    let found = issues(
+        "type,client,tx,amount\n\
+         dispute,1,1,\n\
+         deposit,1,1,1.0\n\
+         withdrawal,1,1,1.0\n\
+         chargeback,1,9,\n",
+    );
+
+    assert_eq!(
+        found,
+        vec![
+            (2, Issue::UnknownReference(1)),
+            (4, Issue::DuplicateTx(1)),
+            (5, Issue::UnknownReference(9)),
+        ]
+    );
+}
+
+#[test]
+fn test_remembers_ids_across_inputs() {
+    let mut validator = Validator::new();
+    validate_csv(&mut validator, "type,client,tx,amount\ndeposit,1,1,1.0\n");
+    let findings = validate_csv(
+        &mut validator,
+        "type,client,tx,amount\ndispute,1,1,\ndeposit,1,1,2.0\n",
+    );
+
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].issue, Issue::DuplicateTx(1));
+    assert_eq!(validator.summary().rows, 3);
+    assert_eq!(validator.summary().rows_with_issues, 1);
+    assert_eq!(validator.summary().issues.get("duplicate_tx"), Some(&1));
+}